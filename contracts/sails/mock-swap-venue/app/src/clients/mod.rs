@@ -0,0 +1 @@
+pub mod extended_vft_client;
@@ -0,0 +1,41 @@
+#![no_std]
+
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        calls::GStdRemoting,
+        msg,
+    }
+};
+
+pub mod clients;
+pub mod services;
+
+//Import the swap venue service from the services module
+use services::swap_venue::SwapVenueService;
+
+//Import the VftClient from the clients module
+use clients::extended_vft_client::Vft as VftClient;
+
+#[derive(Default)]
+pub struct MockSwapVenueProgram;
+
+#[sails_rs::program]
+impl MockSwapVenueProgram {
+
+    // Program's constructor, the attached value funds the VARA side
+    pub fn new(vara_price: u128) -> Self {
+        SwapVenueService::<VftClient<GStdRemoting>>::seed(msg::source(), vara_price);
+
+        Self
+    }
+
+    // Expose swap venue service
+    #[route("SwapVenue")]
+    pub fn swap_venue(&self) -> SwapVenueService<VftClient<GStdRemoting>> {
+        let vft_client = VftClient::new(GStdRemoting);
+
+        SwapVenueService::new(vft_client)
+    }
+
+}
@@ -0,0 +1,148 @@
+use core::ptr::addr_of_mut;
+use sails_rs::calls::Call;
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        exec,
+        msg,
+        CommandReply,
+    }
+};
+
+use crate::clients::extended_vft_client::traits::Vft;
+
+// Raw units in one VARA
+const ONE_VARA: u128 = 1_000_000_000_000;
+
+pub struct SwapVenueState {
+    pub owner: ActorId,
+    // Stable raw units paid for one VARA
+    pub vara_price: u128,
+}
+
+static mut SWAP_VENUE_STATE: Option<SwapVenueState> = None;
+
+pub struct SwapVenueService<VftClient> {
+    pub vft_client: VftClient,
+}
+
+#[sails_rs::service]
+impl<VftClient> SwapVenueService<VftClient>
+where
+    VftClient: Vft,
+{
+    // Service's constructor
+    pub fn seed(owner: ActorId, vara_price: u128) {
+        unsafe {
+            SWAP_VENUE_STATE = Some(SwapVenueState { owner, vara_price });
+        };
+    }
+
+    pub fn new(vft_client: VftClient) -> Self {
+        Self { vft_client }
+    }
+
+    fn state_mut(&self) -> &'static mut SwapVenueState {
+        let state = unsafe { (*addr_of_mut!(SWAP_VENUE_STATE)).as_mut() };
+        debug_assert!(state.is_some(), "The state is not initialized");
+        unsafe { state.unwrap_unchecked() }
+    }
+
+    pub fn set_price(&mut self, vara_price: u128) {
+        let state = self.state_mut();
+        if msg::source() != state.owner {
+            panic!("Only the owner can set the price");
+        }
+        state.vara_price = vara_price;
+    }
+
+    // Pull `amount_in` of `token` from the caller and reply with the VARA it buys
+    pub async fn swap_token_for_vara(
+        &mut self,
+        token: ActorId,
+        amount_in: u128,
+        min_out: u128,
+    ) -> CommandReply<u128> {
+        let caller = msg::source();
+        let amount_out = amount_in.saturating_mul(ONE_VARA) / self.state_mut().vara_price;
+
+        if amount_out == 0 || amount_out < min_out {
+            panic!("Insufficient output amount");
+        }
+
+        let pulled = self
+            .vft_client
+            .transfer_from(caller, exec::program_id(), U256::from(amount_in))
+            .send_recv(token)
+            .await;
+
+        if !matches!(pulled, Ok(true)) {
+            panic!("Token transfer failed");
+        }
+
+        CommandReply::new(amount_out).with_value(amount_out)
+    }
+
+    // Sell the VARA attached to the message and transfer the `token` it buys to the caller
+    pub async fn swap_vara_for_token(&mut self, token: ActorId, min_out: u128) -> u128 {
+        let caller = msg::source();
+        let amount_out = msg::value().saturating_mul(self.state_mut().vara_price) / ONE_VARA;
+
+        if amount_out == 0 || amount_out < min_out {
+            panic!("Insufficient output amount");
+        }
+
+        let sent = self
+            .vft_client
+            .transfer(caller, U256::from(amount_out))
+            .send_recv(token)
+            .await;
+
+        if !matches!(sent, Ok(true)) {
+            panic!("Token transfer failed");
+        }
+
+        amount_out
+    }
+
+    // Swap one token for another at par in raw units
+    pub async fn swap_tokens(
+        &mut self,
+        token_in: ActorId,
+        token_out: ActorId,
+        amount_in: u128,
+        min_out: u128,
+    ) -> u128 {
+        let caller = msg::source();
+
+        if amount_in == 0 || amount_in < min_out {
+            panic!("Insufficient output amount");
+        }
+
+        let pulled = self
+            .vft_client
+            .transfer_from(caller, exec::program_id(), U256::from(amount_in))
+            .send_recv(token_in)
+            .await;
+
+        if !matches!(pulled, Ok(true)) {
+            panic!("Token transfer failed");
+        }
+
+        let sent = self
+            .vft_client
+            .transfer(caller, U256::from(amount_in))
+            .send_recv(token_out)
+            .await;
+
+        if !matches!(sent, Ok(true)) {
+            panic!("Token transfer failed");
+        }
+
+        amount_in
+    }
+
+    pub fn price(&self) -> u128 {
+        self.state_mut().vara_price
+    }
+}
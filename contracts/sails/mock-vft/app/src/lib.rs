@@ -0,0 +1,29 @@
+#![no_std]
+
+use sails_rs::prelude::*;
+
+pub mod services;
+
+//Import the vft service from the services module
+use services::vft::VftService;
+
+#[derive(Default)]
+pub struct MockVftProgram;
+
+#[sails_rs::program]
+impl MockVftProgram {
+
+    // Program's constructor, same signature as the extended VFT one
+    pub fn new(name: String, symbol: String, decimals: u8) -> Self {
+        VftService::seed(name, symbol, decimals);
+
+        Self
+    }
+
+    // Expose vft service
+    #[route("Vft")]
+    pub fn vft(&self) -> VftService {
+        VftService::new()
+    }
+
+}
@@ -0,0 +1,137 @@
+use core::ptr::addr_of_mut;
+use sails_rs::{
+    prelude::*,
+    collections::BTreeMap,
+    gstd::msg,
+};
+
+pub struct VftState {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: U256,
+    pub balances: BTreeMap<ActorId, U256>,
+    pub allowances: BTreeMap<(ActorId, ActorId), U256>,
+}
+
+static mut VFT_STATE: Option<VftState> = None;
+
+pub struct VftService;
+
+#[sails_rs::service]
+impl VftService {
+    // Service's constructor
+    pub fn seed(name: String, symbol: String, decimals: u8) {
+        unsafe {
+            VFT_STATE = Some(VftState {
+                name,
+                symbol,
+                decimals,
+                total_supply: U256::zero(),
+                balances: BTreeMap::new(),
+                allowances: BTreeMap::new(),
+            });
+        };
+    }
+
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn state_mut(&self) -> &'static mut VftState {
+        let state = unsafe { (*addr_of_mut!(VFT_STATE)).as_mut() };
+        debug_assert!(state.is_some(), "The state is not initialized");
+        unsafe { state.unwrap_unchecked() }
+    }
+
+    // Anyone can mint or burn, the roles of the extended VFT are not modelled
+    pub fn mint(&mut self, to: ActorId, value: U256) -> bool {
+        let state = self.state_mut();
+        let balance = state.balances.entry(to).or_default();
+        *balance = balance.saturating_add(value);
+        state.total_supply = state.total_supply.saturating_add(value);
+        true
+    }
+
+    pub fn burn(&mut self, from: ActorId, value: U256) -> bool {
+        let state = self.state_mut();
+        let balance = state.balances.entry(from).or_default();
+        if *balance < value {
+            panic!("Insufficient balance");
+        }
+        *balance -= value;
+        state.total_supply -= value;
+        true
+    }
+
+    pub fn approve(&mut self, spender: ActorId, value: U256) -> bool {
+        self.state_mut()
+            .allowances
+            .insert((msg::source(), spender), value);
+        true
+    }
+
+    pub fn transfer(&mut self, to: ActorId, value: U256) -> bool {
+        self.move_balance(msg::source(), to, value)
+    }
+
+    pub fn transfer_from(&mut self, from: ActorId, to: ActorId, value: U256) -> bool {
+        let spender = msg::source();
+        if spender != from {
+            let allowance = self
+                .state_mut()
+                .allowances
+                .entry((from, spender))
+                .or_default();
+            if *allowance < value {
+                panic!("Insufficient allowance");
+            }
+            *allowance -= value;
+        }
+        self.move_balance(from, to, value)
+    }
+
+    pub fn allowance(&self, owner: ActorId, spender: ActorId) -> U256 {
+        self.state_mut()
+            .allowances
+            .get(&(owner, spender))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn balance_of(&self, account: ActorId) -> U256 {
+        self.state_mut()
+            .balances
+            .get(&account)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.state_mut().decimals
+    }
+
+    pub fn name(&self) -> String {
+        self.state_mut().name.clone()
+    }
+
+    pub fn symbol(&self) -> String {
+        self.state_mut().symbol.clone()
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        self.state_mut().total_supply
+    }
+
+    fn move_balance(&mut self, from: ActorId, to: ActorId, value: U256) -> bool {
+        let balances = &mut self.state_mut().balances;
+        let from_balance = balances.entry(from).or_default();
+        if *from_balance < value {
+            panic!("Insufficient balance");
+        }
+        *from_balance -= value;
+        let to_balance = balances.entry(to).or_default();
+        *to_balance = to_balance.saturating_add(value);
+        true
+    }
+}
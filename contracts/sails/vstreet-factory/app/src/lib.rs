@@ -0,0 +1,35 @@
+#![no_std]
+
+use sails_rs::{
+    prelude::*,
+    gstd::msg,
+};
+
+pub mod states;
+pub mod services;
+
+//Import the Factory service from the services module
+use services::vst_factory::FactoryService;
+
+#[derive(Default)]
+pub struct VstreetFactoryProgram;
+
+#[sails_rs::program]
+impl VstreetFactoryProgram {
+
+    // Program's constructor
+    pub fn new(vstreet_code_id: CodeId) -> Self {
+        let owner = msg::source();
+
+        FactoryService::seed(owner, vec![owner], vstreet_code_id);
+
+        Self
+    }
+
+    // Expose factory service
+    #[route("FactoryService")]
+    pub fn factory(&self) -> FactoryService {
+        FactoryService::new()
+    }
+
+}
@@ -0,0 +1,2 @@
+pub mod vst_factory;
+pub mod utils;
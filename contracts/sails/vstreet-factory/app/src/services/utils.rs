@@ -0,0 +1,7 @@
+pub const ERROR_INSUFFICIENT_ADMIN_PRIVILEGES: &str = "Only an administrator can perform this action";
+pub const ERROR_ADMIN_ALREADY_EXISTS: &str = "Admin already exists";
+pub const ERROR_MARKET_ALREADY_EXISTS: &str = "A market for this pair already exists";
+pub const ERROR_INVALID_LTV: &str = "LTV must be between 1 and 95";
+pub const ERROR_MARKET_DEPLOYMENT_FAILED: &str = "Market deployment failed";
+pub const ERROR_COLLATERAL_SETUP_FAILED: &str = "Market collateral setup failed";
+pub const ERROR_ADMIN_HANDOVER_FAILED: &str = "Market admin handover failed";
@@ -0,0 +1,267 @@
+use sails_rs::calls::{Activation, Call};
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        calls::GStdRemoting,
+        msg,
+    }
+};
+use sails_rs::collections::BTreeMap;
+
+use vstreet_client::traits::{LiquidityInjectionService as _, VstreetFactory as _};
+use vstreet_client::{LiquidityInjectionService as VstreetClient, VstreetFactory};
+
+use crate::states::factory_state::{FactoryState, MarketRecord, CollateralParams};
+use crate::services::utils::{
+    ERROR_INSUFFICIENT_ADMIN_PRIVILEGES,
+    ERROR_ADMIN_ALREADY_EXISTS,
+    ERROR_MARKET_ALREADY_EXISTS,
+    ERROR_INVALID_LTV,
+    ERROR_MARKET_DEPLOYMENT_FAILED,
+    ERROR_COLLATERAL_SETUP_FAILED,
+    ERROR_ADMIN_HANDOVER_FAILED
+};
+
+static mut FACTORY_STATE: Option<FactoryState> = None;
+
+#[derive(Decode, Encode, TypeInfo)]
+pub enum FactoryEvent {
+    MarketCreated{market_id:u64, program_id:ActorId, collateral:ActorId, borrow_asset:ActorId},
+    VstreetCodeIdUpdated{vstreet_code_id:CodeId},
+    AdminAdded{admin:ActorId},
+    Error(String),
+}
+
+pub struct FactoryService;
+
+#[sails_rs::service(events = FactoryEvent)]
+impl FactoryService {
+    // Service's constructor
+    pub fn seed(
+        owner: ActorId,
+        admins: Vec<ActorId>,
+        vstreet_code_id: CodeId,
+    ) {
+        unsafe {
+            FACTORY_STATE = Some(
+                FactoryState {
+                    owner,
+                    admins,
+                    vstreet_code_id,
+                    next_market_id: 0,
+                    markets: BTreeMap::new(),
+                    markets_by_pair: BTreeMap::new(),
+                }
+            );
+        };
+    }
+
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Admin methods
+
+    fn ensure_admin(&mut self) -> Result<(), String> {
+        let state = self.state_ref();
+        let caller = msg::source();
+
+        if !state.admins.contains(&caller) {
+            return Err(self.fail(ERROR_INSUFFICIENT_ADMIN_PRIVILEGES));
+        }
+
+        Ok(())
+    }
+
+    pub fn add_admin(&mut self, new_admin: ActorId) -> Result<(), String> {
+        self.ensure_admin()?;
+
+        let state = self.state_mut();
+
+        if state.admins.contains(&new_admin) {
+            return Err(self.fail(ERROR_ADMIN_ALREADY_EXISTS));
+        }
+
+        state.admins.push(new_admin);
+
+        self.notify_on(FactoryEvent::AdminAdded { admin: new_admin })
+            .expect("Notification Error");
+
+        Ok(())
+    }
+
+    // Point the factory at new vstreet code. Existing markets are untouched.
+    pub fn set_vstreet_code_id(&mut self, vstreet_code_id: CodeId) -> Result<(), String> {
+        self.ensure_admin()?;
+
+        let state = self.state_mut();
+        state.vstreet_code_id = vstreet_code_id;
+
+        self.notify_on(FactoryEvent::VstreetCodeIdUpdated { vstreet_code_id })
+            .expect("Notification Error");
+
+        Ok(())
+    }
+
+    // Deploy a new vstreet market lending `borrow_asset` against VARA. With `collateral`
+    // the VFT is listed on the market as well. The caller becomes a market administrator.
+    // One market per (collateral, borrow asset) pair, VARA-only markets use ActorId::zero().
+    pub async fn create_market(
+        &mut self,
+        borrow_asset: ActorId,
+        ltv: u128,
+        collateral: Option<CollateralParams>,
+    ) -> Result<ActorId, String> {
+        self.ensure_admin()?;
+
+        let state = self.state_mut();
+        let creator = msg::source();
+        let collateral_asset = collateral.as_ref().map_or(ActorId::zero(), |params| params.asset);
+        let pair = (collateral_asset, borrow_asset);
+
+        if state.markets_by_pair.contains_key(&pair) {
+            return Err(self.fail(ERROR_MARKET_ALREADY_EXISTS));
+        }
+
+        if ltv == 0 || ltv > 95 {
+            return Err(self.fail(ERROR_INVALID_LTV));
+        }
+
+        // Reserve the id and the pair BEFORE any await so concurrent calls cannot claim them
+        let market_id = state.next_market_id;
+        state.next_market_id += 1;
+        state.markets_by_pair.insert(pair, market_id);
+
+        let vstreet_code_id = state.vstreet_code_id;
+        let salt = market_id.to_le_bytes();
+
+        match self
+            .deploy_market(creator, borrow_asset, ltv, collateral, vstreet_code_id, salt)
+            .await
+        {
+            Ok(program_id) => {
+                let state = self.state_mut();
+                state.markets.insert(
+                    market_id,
+                    MarketRecord {
+                        program_id,
+                        collateral: collateral_asset,
+                        borrow_asset,
+                        ltv,
+                        creator,
+                    },
+                );
+
+                self.notify_on(FactoryEvent::MarketCreated {
+                    market_id,
+                    program_id,
+                    collateral: collateral_asset,
+                    borrow_asset,
+                })
+                .expect("Notification Error");
+
+                Ok(program_id)
+            }
+            Err(error) => {
+                // Free the pair so the deployment can be retried
+                let state = self.state_mut();
+                state.markets_by_pair.remove(&pair);
+                Err(self.fail(error))
+            }
+        }
+    }
+
+    // Service's queries
+
+    pub fn owner(&self) -> ActorId {
+        self.state_ref().owner
+    }
+
+    pub fn vstreet_code_id(&self) -> CodeId {
+        self.state_ref().vstreet_code_id
+    }
+
+    pub fn markets(&self) -> Vec<(u64, MarketRecord)> {
+        self.state_ref()
+            .markets
+            .iter()
+            .map(|(market_id, record)| (*market_id, record.clone()))
+            .collect()
+    }
+
+    pub fn market_by_pair(&self, collateral: ActorId, borrow_asset: ActorId) -> Option<MarketRecord> {
+        let state = self.state_ref();
+        state
+            .markets_by_pair
+            .get(&(collateral, borrow_asset))
+            .and_then(|market_id| state.markets.get(market_id))
+            .cloned()
+    }
+
+    // Internal methods
+
+    async fn deploy_market(
+        &mut self,
+        creator: ActorId,
+        borrow_asset: ActorId,
+        ltv: u128,
+        collateral: Option<CollateralParams>,
+        vstreet_code_id: CodeId,
+        salt: [u8; 8],
+    ) -> Result<ActorId, &'static str> {
+        let program_id = VstreetFactory::new(GStdRemoting)
+            .new_with_vft(borrow_asset, ltv)
+            .send_recv(vstreet_code_id, salt)
+            .await
+            .map_err(|_| ERROR_MARKET_DEPLOYMENT_FAILED)?;
+
+        let mut vstreet_client = VstreetClient::new(GStdRemoting);
+
+        // The factory owns the new market, so it can list the collateral
+        if let Some(params) = collateral {
+            vstreet_client
+                .add_collateral_asset(
+                    params.asset,
+                    params.price_feed,
+                    params.one_token,
+                    params.collateral_factor,
+                    params.liquidation_threshold,
+                )
+                .send_recv(program_id)
+                .await
+                .map_err(|_| ERROR_COLLATERAL_SETUP_FAILED)?
+                .map_err(|_| ERROR_COLLATERAL_SETUP_FAILED)?;
+        }
+
+        // Hand administration to the creator
+        vstreet_client
+            .add_admin(creator)
+            .send_recv(program_id)
+            .await
+            .map_err(|_| ERROR_ADMIN_HANDOVER_FAILED)?
+            .map_err(|_| ERROR_ADMIN_HANDOVER_FAILED)?;
+
+        Ok(program_id)
+    }
+
+    fn fail(&mut self, error: &str) -> String {
+        let error_message = error.to_string();
+
+        self.notify_on(FactoryEvent::Error(error_message.clone()))
+            .expect("Notification Error");
+
+        error_message
+    }
+
+    fn state_mut(&self) -> &'static mut FactoryState {
+        let state = unsafe { FACTORY_STATE.as_mut() };
+        debug_assert!(state.is_some(), "state is not started!");
+        unsafe { state.unwrap_unchecked() }
+    }
+
+    fn state_ref(&self) -> &'static FactoryState {
+        let state = unsafe { FACTORY_STATE.as_ref() };
+        debug_assert!(state.is_some(), "state is not started!");
+        unsafe { state.unwrap_unchecked() }
+    }
+}
@@ -0,0 +1,36 @@
+use core::fmt::Debug;
+use sails_rs::{
+    collections::BTreeMap,
+    prelude::*,
+};
+
+// VFT collateral listed on a new market next to VARA, see `add_collateral_asset`
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct CollateralParams {
+    pub asset: ActorId,
+    pub price_feed: ActorId,
+    pub one_token: u128,
+    pub collateral_factor: u128,
+    pub liquidation_threshold: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct MarketRecord {
+    pub program_id: ActorId,
+    // ActorId::zero() stands for a market that only takes native VARA
+    pub collateral: ActorId,
+    pub borrow_asset: ActorId,
+    pub ltv: u128,
+    pub creator: ActorId,
+}
+
+#[derive(Clone, Encode, TypeInfo)]
+pub struct FactoryState {
+    pub owner: ActorId,
+    pub admins: Vec<ActorId>,
+    pub vstreet_code_id: CodeId,
+    pub next_market_id: u64,
+    pub markets: BTreeMap<u64, MarketRecord>,
+    // (collateral, borrow asset) -> market id
+    pub markets_by_pair: BTreeMap<(ActorId, ActorId), u64>,
+}
@@ -0,0 +1 @@
+pub mod factory_state;
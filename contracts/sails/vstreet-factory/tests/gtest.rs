@@ -0,0 +1,159 @@
+use sails_rs::{calls::*, gtest::{calls::*, System}, ActorId};
+use vstreet_factory_client::traits::*;
+use vstreet_client::traits::LiquidityInjectionService as _;
+use vstreet_factory_client::CollateralParams;
+
+const ACTOR_ID: u64 = 42;
+const ACTOR_ID_2: u64 = 44;
+const BORROW_ASSET_ID: u64 = 43;
+const COLLATERAL_TOKEN_ID: u64 = 45;
+const LTV: u128 = 70;
+
+// Setup Helpers
+
+async fn setup_system() -> (GTestRemoting, ActorId) {
+    let system = System::new();
+    system.init_logger();
+    system.mint_to(ACTOR_ID, 100_000_000_000_000);
+    system.mint_to(ACTOR_ID_2, 100_000_000_000_000);
+
+    let remoting = GTestRemoting::new(system, ACTOR_ID.into());
+    remoting.system().init_logger();
+
+    let vstreet_code_id = remoting.system().submit_code(vstreet::WASM_BINARY);
+    let factory_code_id = remoting.system().submit_code(vstreet_factory::WASM_BINARY);
+
+    let program_factory = vstreet_factory_client::VstreetFactoryFactory::new(remoting.clone());
+
+    let factory_id = program_factory
+        .new(vstreet_code_id)
+        .send_recv(factory_code_id, b"salt")
+        .await
+        .unwrap();
+
+    // The factory pays the existential deposit of the programs it creates
+    remoting.system().mint_to(factory_id, 100_000_000_000_000);
+
+    (remoting, factory_id)
+}
+
+// Listing of the test collateral token, with its price pushed by ACTOR_ID_2
+fn collateral(collateral_factor: u128, liquidation_threshold: u128) -> CollateralParams {
+    CollateralParams {
+        asset: COLLATERAL_TOKEN_ID.into(),
+        price_feed: ACTOR_ID_2.into(),
+        one_token: 1_000_000,
+        collateral_factor,
+        liquidation_threshold,
+    }
+}
+
+#[tokio::test]
+async fn test_create_market_end_to_end() {
+    let (remoting, factory_id) = setup_system().await;
+    let mut factory_client = vstreet_factory_client::FactoryService::new(remoting.clone());
+
+    let program_id = factory_client
+        .create_market(BORROW_ASSET_ID.into(), LTV, Some(collateral(60, 75)))
+        .send_recv(factory_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Registry lookup by pair
+    let record = factory_client
+        .market_by_pair(COLLATERAL_TOKEN_ID.into(), BORROW_ASSET_ID.into())
+        .recv(factory_id)
+        .await
+        .unwrap()
+        .expect("market registered");
+    assert_eq!(record.program_id, program_id);
+    assert_eq!(record.ltv, LTV);
+    assert_eq!(record.creator, ACTOR_ID.into());
+
+    let markets = factory_client.markets().recv(factory_id).await.unwrap();
+    assert_eq!(markets.len(), 1);
+
+    // The deployed market points at the borrow asset and lists the collateral
+    let vstreet_client = vstreet_client::LiquidityInjectionService::new(remoting.clone());
+    let vft_contract_id = vstreet_client.vft_contract_id().recv(program_id).await.unwrap();
+    assert!(vft_contract_id.contains(&format!("{:?}", ActorId::from(BORROW_ASSET_ID))));
+
+    let assets = vstreet_client.collateral_assets().recv(program_id).await.unwrap();
+    assert!(assets.contains(&format!("{:?}", ActorId::from(COLLATERAL_TOKEN_ID))));
+    assert!(assets.contains("collateral_factor: 60"));
+    assert!(assets.contains("liquidation_threshold: 75"));
+
+    // The creator administers the market
+    let mut creator_client = vstreet_client::LiquidityInjectionService::new(remoting.clone());
+    let result = creator_client
+        .set_supply_cap(1_000)
+        .send_recv(program_id)
+        .await
+        .unwrap();
+    assert!(result.is_ok());
+
+    // A second market for the same pair is rejected
+    let result = factory_client
+        .create_market(BORROW_ASSET_ID.into(), LTV, Some(collateral(60, 75)))
+        .send_recv(factory_id)
+        .await
+        .unwrap();
+    assert_eq!(result, Err("A market for this pair already exists".to_string()));
+
+    // A VARA-only market for the same borrow asset is a different pair
+    let vara_program_id = factory_client
+        .create_market(BORROW_ASSET_ID.into(), LTV, None)
+        .send_recv(factory_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let record = factory_client
+        .market_by_pair(ActorId::zero(), BORROW_ASSET_ID.into())
+        .recv(factory_id)
+        .await
+        .unwrap()
+        .expect("market registered");
+    assert_eq!(record.program_id, vara_program_id);
+}
+
+#[tokio::test]
+async fn test_create_market_rejects_invalid_collateral() {
+    let (remoting, factory_id) = setup_system().await;
+    let mut factory_client = vstreet_factory_client::FactoryService::new(remoting.clone());
+
+    // Collateral factor above the liquidation threshold is refused by the market
+    let result = factory_client
+        .create_market(BORROW_ASSET_ID.into(), LTV, Some(collateral(80, 70)))
+        .send_recv(factory_id)
+        .await
+        .unwrap();
+    assert_eq!(result, Err("Market collateral setup failed".to_string()));
+
+    // The pair is free again
+    let record = factory_client
+        .market_by_pair(COLLATERAL_TOKEN_ID.into(), BORROW_ASSET_ID.into())
+        .recv(factory_id)
+        .await
+        .unwrap();
+    assert!(record.is_none());
+}
+
+#[tokio::test]
+async fn test_create_market_requires_admin() {
+    let (remoting, factory_id) = setup_system().await;
+    let mut outsider_client = vstreet_factory_client::FactoryService::new(
+        remoting.clone().with_actor_id(ACTOR_ID_2.into()),
+    );
+
+    let result = outsider_client
+        .create_market(BORROW_ASSET_ID.into(), LTV, None)
+        .send_recv(factory_id)
+        .await
+        .unwrap();
+    assert_eq!(result, Err("Only an administrator can perform this action".to_string()));
+
+    let markets = outsider_client.markets().recv(factory_id).await.unwrap();
+    assert!(markets.is_empty());
+}
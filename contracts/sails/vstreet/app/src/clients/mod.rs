@@ -1 +1,2 @@
 pub mod extended_vft_client;
+pub mod swap_venue_client;
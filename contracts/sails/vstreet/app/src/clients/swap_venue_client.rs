@@ -0,0 +1,214 @@
+// Code generated by sails-client-gen. DO NOT EDIT.
+#[allow(unused_imports)]
+use sails_rs::collections::BTreeMap;
+#[allow(unused_imports)]
+use sails_rs::{
+    calls::{Activation, Call, Query, Remoting, RemotingAction},
+    prelude::*,
+    String,
+};
+pub struct SwapVenueFactory<R> {
+    #[allow(dead_code)]
+    remoting: R,
+}
+impl<R> SwapVenueFactory<R> {
+    #[allow(unused)]
+    pub fn new(remoting: R) -> Self {
+        Self { remoting }
+    }
+}
+impl<R: Remoting + Clone> traits::SwapVenueFactory for SwapVenueFactory<R> {
+    type Args = R::Args;
+    fn new(&self, vara_price: u128) -> impl Activation<Args = R::Args> {
+        RemotingAction::<_, swap_venue_factory::io::New>::new(self.remoting.clone(), vara_price)
+    }
+}
+pub mod swap_venue_factory {
+    use super::*;
+    pub mod io {
+        use super::*;
+        use sails_rs::calls::ActionIo;
+        pub struct New(());
+        impl New {
+            #[allow(dead_code)]
+            pub fn encode_call(vara_price: u128) -> Vec<u8> {
+                <New as ActionIo>::encode_call(&vara_price)
+            }
+        }
+        impl ActionIo for New {
+            const ROUTE: &'static [u8] = &[12, 78, 101, 119];
+            type Params = u128;
+            type Reply = ();
+        }
+    }
+}
+pub struct SwapVenue<R> {
+    remoting: R,
+}
+impl<R> SwapVenue<R> {
+    pub fn new(remoting: R) -> Self {
+        Self { remoting }
+    }
+}
+impl<R: Remoting + Clone> traits::SwapVenue for SwapVenue<R> {
+    type Args = R::Args;
+    fn set_price(&mut self, vara_price: u128) -> impl Call<Output = (), Args = R::Args> {
+        RemotingAction::<_, swap_venue::io::SetPrice>::new(self.remoting.clone(), vara_price)
+    }
+    fn swap_token_for_vara(
+        &mut self,
+        token: ActorId,
+        amount_in: u128,
+        min_out: u128,
+    ) -> impl Call<Output = u128, Args = R::Args> {
+        RemotingAction::<_, swap_venue::io::SwapTokenForVara>::new(
+            self.remoting.clone(),
+            (token, amount_in, min_out),
+        )
+    }
+    fn swap_vara_for_token(
+        &mut self,
+        token: ActorId,
+        min_out: u128,
+    ) -> impl Call<Output = u128, Args = R::Args> {
+        RemotingAction::<_, swap_venue::io::SwapVaraForToken>::new(
+            self.remoting.clone(),
+            (token, min_out),
+        )
+    }
+    fn swap_tokens(
+        &mut self,
+        token_in: ActorId,
+        token_out: ActorId,
+        amount_in: u128,
+        min_out: u128,
+    ) -> impl Call<Output = u128, Args = R::Args> {
+        RemotingAction::<_, swap_venue::io::SwapTokens>::new(
+            self.remoting.clone(),
+            (token_in, token_out, amount_in, min_out),
+        )
+    }
+    fn price(&self) -> impl Query<Output = u128, Args = R::Args> {
+        RemotingAction::<_, swap_venue::io::Price>::new(self.remoting.clone(), ())
+    }
+}
+pub mod swap_venue {
+    use super::*;
+    pub mod io {
+        use super::*;
+        use sails_rs::calls::ActionIo;
+        pub struct SetPrice(());
+        impl SetPrice {
+            #[allow(dead_code)]
+            pub fn encode_call(vara_price: u128) -> Vec<u8> {
+                <SetPrice as ActionIo>::encode_call(&vara_price)
+            }
+        }
+        impl ActionIo for SetPrice {
+            const ROUTE: &'static [u8] = &[
+                36, 83, 119, 97, 112, 86, 101, 110, 117, 101, 32, 83, 101, 116, 80, 114, 105, 99,
+                101,
+            ];
+            type Params = u128;
+            type Reply = ();
+        }
+        pub struct SwapTokenForVara(());
+        impl SwapTokenForVara {
+            #[allow(dead_code)]
+            pub fn encode_call(token: ActorId, amount_in: u128, min_out: u128) -> Vec<u8> {
+                <SwapTokenForVara as ActionIo>::encode_call(&(token, amount_in, min_out))
+            }
+        }
+        impl ActionIo for SwapTokenForVara {
+            const ROUTE: &'static [u8] = &[
+                36, 83, 119, 97, 112, 86, 101, 110, 117, 101, 64, 83, 119, 97, 112, 84, 111, 107,
+                101, 110, 70, 111, 114, 86, 97, 114, 97,
+            ];
+            type Params = (ActorId, u128, u128);
+            type Reply = u128;
+        }
+        pub struct SwapVaraForToken(());
+        impl SwapVaraForToken {
+            #[allow(dead_code)]
+            pub fn encode_call(token: ActorId, min_out: u128) -> Vec<u8> {
+                <SwapVaraForToken as ActionIo>::encode_call(&(token, min_out))
+            }
+        }
+        impl ActionIo for SwapVaraForToken {
+            const ROUTE: &'static [u8] = &[
+                36, 83, 119, 97, 112, 86, 101, 110, 117, 101, 64, 83, 119, 97, 112, 86, 97, 114,
+                97, 70, 111, 114, 84, 111, 107, 101, 110,
+            ];
+            type Params = (ActorId, u128);
+            type Reply = u128;
+        }
+        pub struct SwapTokens(());
+        impl SwapTokens {
+            #[allow(dead_code)]
+            pub fn encode_call(
+                token_in: ActorId,
+                token_out: ActorId,
+                amount_in: u128,
+                min_out: u128,
+            ) -> Vec<u8> {
+                <SwapTokens as ActionIo>::encode_call(&(token_in, token_out, amount_in, min_out))
+            }
+        }
+        impl ActionIo for SwapTokens {
+            const ROUTE: &'static [u8] = &[
+                36, 83, 119, 97, 112, 86, 101, 110, 117, 101, 40, 83, 119, 97, 112, 84, 111, 107,
+                101, 110, 115,
+            ];
+            type Params = (ActorId, ActorId, u128, u128);
+            type Reply = u128;
+        }
+        pub struct Price(());
+        impl Price {
+            #[allow(dead_code)]
+            pub fn encode_call() -> Vec<u8> {
+                <Price as ActionIo>::encode_call(&())
+            }
+        }
+        impl ActionIo for Price {
+            const ROUTE: &'static [u8] = &[
+                36, 83, 119, 97, 112, 86, 101, 110, 117, 101, 20, 80, 114, 105, 99, 101,
+            ];
+            type Params = ();
+            type Reply = u128;
+        }
+    }
+}
+pub mod traits {
+    use super::*;
+    #[allow(dead_code)]
+    pub trait SwapVenueFactory {
+        type Args;
+        #[allow(clippy::new_ret_no_self)]
+        #[allow(clippy::wrong_self_convention)]
+        fn new(&self, vara_price: u128) -> impl Activation<Args = Self::Args>;
+    }
+    #[allow(clippy::type_complexity)]
+    pub trait SwapVenue {
+        type Args;
+        fn set_price(&mut self, vara_price: u128) -> impl Call<Output = (), Args = Self::Args>;
+        fn swap_token_for_vara(
+            &mut self,
+            token: ActorId,
+            amount_in: u128,
+            min_out: u128,
+        ) -> impl Call<Output = u128, Args = Self::Args>;
+        fn swap_vara_for_token(
+            &mut self,
+            token: ActorId,
+            min_out: u128,
+        ) -> impl Call<Output = u128, Args = Self::Args>;
+        fn swap_tokens(
+            &mut self,
+            token_in: ActorId,
+            token_out: ActorId,
+            amount_in: u128,
+            min_out: u128,
+        ) -> impl Call<Output = u128, Args = Self::Args>;
+        fn price(&self) -> impl Query<Output = u128, Args = Self::Args>;
+    }
+}
@@ -6,13 +6,21 @@ use sails_rs::{
     }
 };
 
+use sails_rs::gstd::calls::GStdArgs;
 use crate::clients::extended_vft_client::traits::Vft;
 use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{term_loans, emode, isolation, cdp};
 use crate::services::utils::{
     EventNotifier,
     ERROR_TRANSFER_FAILED,
     ERROR_INVALID_AMOUNT,
-    ERROR_USER_NOT_FOUND
+    ERROR_USER_NOT_FOUND,
+    ERROR_BORROW_CAP_EXCEEDED,
+    ERROR_DELEGATION_ALLOWANCE_EXCEEDED,
+    ERROR_INVALID_DELEGATEE,
+    ERROR_EMODE_ASSET_NOT_ALLOWED,
+    ERROR_ISOLATION_ASSET_NOT_ALLOWED,
+    ERROR_CDP_DEBT_CEILING_EXCEEDED
 };
 
 // Public methods
@@ -23,18 +31,110 @@ pub async fn take_loan<VftClient>(
     amount: u128,
 ) -> Result<(), String> 
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     let caller = msg::source();
 
+    take_loan_for(service, caller, caller, amount).await
+}
+
+//Approve Delegation
+// Lets `delegatee` borrow up to `amount` against the caller's collateral. Setting 0 revokes it.
+pub fn approve_delegation<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    delegatee: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let delegator = msg::source();
+    let state_mut = service.state_mut();
+
+    if delegatee == delegator {
+        let error_message = ERROR_INVALID_DELEGATEE.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Token collateral counts as well, and revoking is always allowed
+    let has_borrowing_power = state_mut
+        .users
+        .get(&delegator)
+        .map(|user_info| LiquidityInjectionService::<VftClient>::max_loan_amount(state_mut, user_info) > 0)
+        .unwrap_or(false);
+
+    if amount > 0 && !has_borrowing_power {
+        let error_message = "Insufficient collateral: no borrowing power".to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 {
+        state_mut.delegations.remove(&(delegator, delegatee));
+    } else {
+        state_mut.delegations.insert((delegator, delegatee), amount);
+    }
+
+    service.notify_delegation_approved(delegator, delegatee, amount);
+
+    Ok(())
+}
+
+//Take Loan on behalf of a delegator
+// The debt is charged to the delegator while the borrowed tokens go to the caller.
+pub async fn take_loan_on_behalf<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    delegator: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let delegatee = msg::source();
+    let state_mut = service.state_mut();
+
+    let allowance = state_mut
+        .delegations
+        .get(&(delegator, delegatee))
+        .copied()
+        .unwrap_or(0);
+
+    if amount > allowance {
+        let error_message = ERROR_DELEGATION_ALLOWANCE_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Consume the allowance before the loan is disbursed and give it back if the loan fails
+    state_mut.delegations.insert((delegator, delegatee), allowance - amount);
+
+    if let Err(error_message) = take_loan_for(service, delegator, delegatee, amount).await {
+        let state_mut = service.state_mut();
+        state_mut.delegations.insert((delegator, delegatee), allowance);
+        return Err(error_message);
+    }
+
+    Ok(())
+}
+
+async fn take_loan_for<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    borrower: ActorId,
+    receiver: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
     // Refresh accrued interest BEFORE reading MLA so the eligibility check
     // uses the real (up-to-date) outstanding loan amount.
-    let _ = service.calculate_loan_interest_rate_amount(caller);
+    let _ = service.calculate_loan_interest_rate_amount(borrower);
 
     // Ensure user exists
     {
         let state_mut = service.state_mut();
-        if !state_mut.users.contains_key(&caller) {
+        if !state_mut.users.contains_key(&borrower) {
             let error_message = ERROR_USER_NOT_FOUND.to_string();
             service.notify_error(error_message.clone());
             return Err(error_message);
@@ -42,10 +142,22 @@ where
     }
 
     // Refresh MLA after interest has been applied
-    let _ = service.calculate_mla(caller);
+    let _ = service.calculate_mla(borrower);
     let state_mut = service.state_mut();
+
+    if !emode::can_borrow_primary(state_mut, &borrower) {
+        let error_message = ERROR_EMODE_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !isolation::can_borrow_primary(state_mut, &borrower) {
+        let error_message = ERROR_ISOLATION_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
     let decimals_factor = state_mut.config.decimals_factor;
-    let user_info = state_mut.users.get_mut(&caller).unwrap();
+    let user_info = state_mut.users.get_mut(&borrower).unwrap();
 
     let mla = user_info.mla;
     let loan_amount = user_info.loan_amount;
@@ -73,6 +185,26 @@ where
         return Err(error_message);
     }
 
+    // Enforce the protocol-wide borrow cap
+    if state_mut.total_borrowed.saturating_add(scaled_amount) > state_mut.config.borrow_cap {
+        let error_message = ERROR_BORROW_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Vaults mint against a global debt ceiling instead of supplier liquidity
+    let exceeds_debt_ceiling = state_mut
+        .cdp
+        .as_ref()
+        .map(|cdp| state_mut.total_borrowed.saturating_add(scaled_amount) > cdp.debt_ceiling)
+        .unwrap_or(false);
+
+    if exceeds_debt_ceiling {
+        let error_message = ERROR_CDP_DEBT_CEILING_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
     // CEI: update all loan state BEFORE the external transfer to prevent re-entrancy.
     // If the transfer later fails we roll back.
     user_info.is_loan_active = true;
@@ -105,30 +237,35 @@ where
             error_message
         })?;
 
-    service.update_user_ltv(caller);
+    service.update_user_ltv(borrower);
     service.refresh_rates();
 
-    let _ = service.calculate_mla(caller);
+    let _ = service.calculate_mla(borrower);
 
     LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
 
-    // Transfer tokens from contract to user AFTER state has been updated (CEI).
-    let result = service.transfer_tokens(exec::program_id(), caller, amount).await;
+    // Transfer (or mint, in CDP mode) tokens to the user AFTER state has been updated (CEI).
+    let result = cdp::disburse(service, receiver, amount).await;
 
     // Roll back state if the transfer fails
     if let Err(_) = result {
         let state_mut = service.state_mut();
-        let user_info = state_mut.users.get_mut(&caller).unwrap();
-        user_info.is_loan_active = false;
+        let has_secondary_debt = term_loans::secondary_debt_value(state_mut, &borrower) > 0;
+        let user_info = state_mut.users.get_mut(&borrower).unwrap();
         user_info.loan_amount = user_info.loan_amount.saturating_sub(scaled_amount);
         user_info.loan_amount_usdc = user_info.loan_amount / decimals_factor;
+        user_info.is_loan_active = user_info.loan_amount > 0 || has_secondary_debt;
         state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(scaled_amount);
         let error_message = ERROR_TRANSFER_FAILED.to_string();
         service.notify_error(error_message.clone());
         return sails_rs::Err(error_message);
     }
 
-    service.notify_loan_taken(amount);
+    if receiver == borrower {
+        service.notify_loan_taken(amount);
+    } else {
+        service.notify_loan_taken_on_behalf(borrower, receiver, amount);
+    }
 
     Ok(())
 }
@@ -138,7 +275,7 @@ pub async fn pay_all_loan<VftClient>(
     service: &mut LiquidityInjectionService<VftClient>
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     let caller = msg::source();
     
@@ -172,8 +309,10 @@ where
     // CEI: update state BEFORE the external transfer to prevent re-entrancy.
     {
         let state_mut = service.state_mut();
+        // Reserve and term loans keep the position active (and its collateral locked)
+        let has_secondary_debt = term_loans::secondary_debt_value(state_mut, &caller) > 0;
         let user_info = state_mut.users.get_mut(&caller).unwrap();
-        user_info.is_loan_active = false;
+        user_info.is_loan_active = has_secondary_debt;
         user_info.loan_amount = 0;
         user_info.loan_amount_usdc = 0;
 
@@ -187,8 +326,8 @@ where
             })?;
     }
 
-    // Transfer tokens from user to contract AFTER state update (CEI).
-    let result = service.transfer_tokens(caller, exec::program_id(), loan_amount).await;
+    // Transfer (or burn, in CDP mode) tokens from user AFTER state update (CEI).
+    let result = cdp::collect(service, caller, loan_amount).await;
 
     // If transfer fails, roll back state
     if let Err(_) = result {
@@ -223,17 +362,45 @@ pub async fn pay_loan<VftClient>(
     amount: u128
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     let caller = msg::source();
-    
+
+    pay_loan_for(service, caller, caller, amount).await
+}
+
+//Repay Loan on behalf of another user
+// The payer's tokens settle the borrower's debt, so rescuers never need the borrower's keys.
+pub async fn repay_for<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    borrower: ActorId,
+    amount: u128
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let payer = msg::source();
+
+    pay_loan_for(service, payer, borrower, amount).await
+}
+
+async fn pay_loan_for<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    payer: ActorId,
+    borrower: ActorId,
+    amount: u128
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
     // Calculate and apply accrued loan interest before payment
-    let _ = service.calculate_loan_interest_rate_amount(caller);
+    let _ = service.calculate_loan_interest_rate_amount(borrower);
     
     let state_mut = service.state_mut();
     let decimals_factor = state_mut.config.decimals_factor;
+    let has_secondary_debt = term_loans::secondary_debt_value(state_mut, &borrower) > 0;
 
-    let user_info = match state_mut.users.get_mut(&caller) {
+    let user_info = match state_mut.users.get_mut(&borrower) {
         Some(user_info) => user_info,
         None => {
             let error_message = ERROR_USER_NOT_FOUND.to_string();
@@ -268,7 +435,7 @@ where
         })?;
 
     if user_info.loan_amount == 0 {
-        user_info.is_loan_active = false;
+        user_info.is_loan_active = has_secondary_debt;
     }
 
     state_mut.total_borrowed = state_mut
@@ -280,13 +447,13 @@ where
             error_message
         })?;
 
-    // Transfer tokens from user to contract AFTER state update (CEI).
-    let result = service.transfer_tokens(caller, exec::program_id(), amount).await;
+    // Transfer (or burn, in CDP mode) tokens from payer AFTER state update (CEI).
+    let result = cdp::collect(service, payer, amount).await;
 
     // If transfer fails, roll back state
     if let Err(_) = result {
         let state_mut = service.state_mut();
-        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        let user_info = state_mut.users.get_mut(&borrower).unwrap();
         user_info.loan_amount = user_info.loan_amount.saturating_add(amount);
         user_info.loan_amount_usdc = user_info.loan_amount / decimals_factor;
         if user_info.loan_amount > 0 {
@@ -298,13 +465,17 @@ where
         return sails_rs::Err(error_message);
     }
 
-    service.update_user_ltv(caller);
-    let _ = service.calculate_mla(caller);
+    service.update_user_ltv(borrower);
+    let _ = service.calculate_mla(borrower);
     LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
 
     service.refresh_rates();
     
-    service.notify_loan_payed(amount);
+    if payer == borrower {
+        service.notify_loan_payed(amount);
+    } else {
+        service.notify_loan_repaid_for(payer, borrower, amount);
+    }
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,257 @@
+use sails_rs::calls::{Action, Call};
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::clients::extended_vft_client::vft::io::{Mint, Burn};
+use crate::services::vst_liquidity_injection::{LiquidityInjectionService, LiquidityEvent};
+use crate::services::term_loans;
+use crate::services::recovery;
+use crate::states::vstreet_state::Cdp;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_USER_NOT_FOUND,
+    ERROR_CDP_NOT_ENABLED,
+    ERROR_CDP_OUTSTANDING_DEBT,
+    ERROR_VAULT_NOT_LIQUIDATABLE
+};
+
+// Internal helpers
+
+// Mint the primary VFT, vstreet must hold the minter role
+pub(crate) async fn mint_stable<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    to: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let contract_id = service.state_mut().vft_contract_id.ok_or_else(|| {
+        "VFT contract ID not configured".to_string()
+    })?;
+
+    let args = recovery::call_args(service, vec![to], recovery::returned_true::<Mint>);
+    let response = service
+        .vft_client
+        .mint(to, U256::from(amount))
+        .with_args(args)
+        .send_recv(contract_id)
+        .await;
+
+    match response {
+        Ok(true) => Ok(()),
+        _ => {
+            let error_message = ERROR_TRANSFER_FAILED.to_string();
+            service.notify_error(error_message.clone());
+            Err(error_message)
+        }
+    }
+}
+
+// Burn the primary VFT from `from`, vstreet must hold the burner role
+pub(crate) async fn burn_stable<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    from: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let contract_id = service.state_mut().vft_contract_id.ok_or_else(|| {
+        "VFT contract ID not configured".to_string()
+    })?;
+
+    let args = recovery::call_args(service, vec![from], recovery::returned_true::<Burn>);
+    let response = service
+        .vft_client
+        .burn(from, U256::from(amount))
+        .with_args(args)
+        .send_recv(contract_id)
+        .await;
+
+    match response {
+        Ok(true) => Ok(()),
+        _ => {
+            let error_message = ERROR_TRANSFER_FAILED.to_string();
+            service.notify_error(error_message.clone());
+            Err(error_message)
+        }
+    }
+}
+
+// Hand out borrowed primary tokens: minted in CDP mode, lent from the pool otherwise
+pub(crate) async fn disburse<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    to: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if service.defer_primary(exec::program_id(), amount) {
+        return Ok(());
+    }
+
+    if service.state_mut().cdp.is_some() {
+        mint_stable(service, to, amount).await
+    } else {
+        service.transfer_tokens(exec::program_id(), to, amount).await
+    }
+}
+
+// Take repaid primary tokens back: burned in CDP mode, returned to the pool otherwise
+pub(crate) async fn collect<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    from: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if service.defer_primary(from, amount) {
+        return Ok(());
+    }
+
+    if service.state_mut().cdp.is_some() {
+        burn_stable(service, from, amount).await
+    } else {
+        service.transfer_tokens(from, exec::program_id(), amount).await
+    }
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// Switch the market into CDP mode with `stablecoin` as the primary VFT, or update
+// the stability fee and debt ceiling. The stablecoin can only change without outstanding debt.
+pub fn set_cdp<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    stablecoin: ActorId,
+    stability_fee: u128,
+    debt_ceiling: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    // Settle fees at the old rate before changing it
+    let _ = service.calculate_all_loan_interest_rate_amounts();
+
+    let state_mut = service.state_mut();
+
+    if state_mut.vft_contract_id != Some(stablecoin) && state_mut.total_borrowed > 0 {
+        let error_message = ERROR_CDP_OUTSTANDING_DEBT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let accrued_fees = state_mut.cdp.as_ref().map(|cdp| cdp.accrued_fees).unwrap_or(0);
+
+    state_mut.vft_contract_id = Some(stablecoin);
+    state_mut.cdp = Some(Cdp {
+        stability_fee,
+        debt_ceiling,
+        accrued_fees,
+    });
+
+    service.notify_cdp_updated(stablecoin, stability_fee, debt_ceiling);
+
+    Ok(())
+}
+
+// Public methods
+
+// Liquidate an unhealthy vault: the caller burns the vault's stablecoin debt
+// and receives the VARA collateral locked against it.
+pub async fn liquidate_vault<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    user: ActorId,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let liquidator = msg::source();
+
+    if service.state_mut().cdp.is_none() {
+        let error_message = ERROR_CDP_NOT_ENABLED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Apply accrued stability fees so the liquidator burns the full debt
+    let _ = service.calculate_loan_interest_rate_amount(user);
+    service.update_user_ltv(user);
+
+    let state_mut = service.state_mut();
+    let decimals_factor = state_mut.config.decimals_factor;
+    let has_secondary_debt = term_loans::secondary_debt_value(state_mut, &user) > 0;
+
+    let user_info = match state_mut.users.get_mut(&user) {
+        Some(user_info) => user_info,
+        None => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    let debt = user_info.loan_amount;
+    let seized = user_info.balance_vara.saturating_mul(user_info.ltv) / 100;
+
+    if debt == 0 || seized == 0 || user_info.ltv < user_info.liquidation_threshold {
+        let error_message = ERROR_VAULT_NOT_LIQUIDATABLE.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: close the vault BEFORE burning the liquidator's stablecoin
+    user_info.loan_amount = 0;
+    user_info.loan_amount_usdc = 0;
+    user_info.is_loan_active = has_secondary_debt;
+    user_info.balance_vara = user_info.balance_vara.saturating_sub(seized);
+    state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_sub(seized);
+    state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(debt);
+
+    if let Err(error_message) = burn_stable(service, liquidator, debt).await {
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&user).unwrap();
+        user_info.loan_amount = debt;
+        user_info.loan_amount_usdc = debt / decimals_factor;
+        user_info.is_loan_active = true;
+        user_info.balance_vara = user_info.balance_vara.saturating_add(seized);
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(seized);
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(debt);
+        return Err(error_message);
+    }
+
+    term_loans::refresh_user_position(service, user);
+
+    // The debt is already burned, so if sending VARA fails the liquidator is
+    // credited the seized amount as collateral and can withdraw it later.
+    if msg::send(
+        liquidator,
+        LiquidityEvent::VaultLiquidated { user, liquidator, debt, collateral_seized: seized },
+        seized,
+    ).is_err() {
+        let state_mut = service.state_mut();
+        let current_timestamp = exec::block_timestamp() as u128;
+        let liquidator_info = state_mut
+            .users
+            .entry(liquidator)
+            .or_insert_with(|| LiquidityInjectionService::<VftClient>::create_new_user(current_timestamp));
+        liquidator_info.balance_vara = liquidator_info.balance_vara.saturating_add(seized);
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(seized);
+        term_loans::refresh_user_position(service, liquidator);
+    }
+
+    service.notify_vault_liquidated(user, liquidator, debt, seized);
+
+    Ok(())
+}
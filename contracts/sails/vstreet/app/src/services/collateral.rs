@@ -0,0 +1,340 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{isolation, term_loans};
+use crate::states::vstreet_state::CollateralAsset;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT,
+    ERROR_USER_NOT_FOUND,
+    ERROR_INSUFFICIENT_ADMIN_PRIVILEGES,
+    ERROR_COLLATERAL_ASSET_NOT_FOUND,
+    ERROR_COLLATERAL_ASSET_ALREADY_EXISTS,
+    ERROR_COLLATERAL_ASSET_DISABLED,
+    ERROR_INVALID_COLLATERAL_PARAMS,
+    ERROR_INSUFFICIENT_COLLATERAL,
+    ERROR_ISOLATED_COLLATERAL_MIXING
+};
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// Collateral factor and liquidation threshold are percentages, the factor
+// must stay below the threshold so new loans are never born liquidatable.
+fn validate_collateral_params(collateral_factor: u128, liquidation_threshold: u128) -> bool {
+    collateral_factor > 0
+        && collateral_factor <= 95
+        && liquidation_threshold >= collateral_factor
+        && liquidation_threshold <= 100
+}
+
+// List a new VFT collateral asset
+pub fn add_collateral_asset<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    price_feed: ActorId,
+    one_token: u128,
+    collateral_factor: u128,
+    liquidation_threshold: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    if state_mut.collateral_assets.contains_key(&asset) {
+        let error_message = ERROR_COLLATERAL_ASSET_ALREADY_EXISTS.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if one_token == 0 || !validate_collateral_params(collateral_factor, liquidation_threshold) {
+        let error_message = ERROR_INVALID_COLLATERAL_PARAMS.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // The price starts at 0 so the asset carries no borrowing power until its feed reports
+    state_mut.collateral_assets.insert(
+        asset,
+        CollateralAsset {
+            price_feed,
+            price: 0,
+            one_token,
+            collateral_factor,
+            liquidation_threshold,
+            total_deposited: 0,
+            enabled: true,
+            debt_ceiling: 0,
+            isolation_borrowable: Vec::new(),
+        },
+    );
+
+    service.notify_collateral_asset_listed(asset);
+
+    Ok(())
+}
+
+// Change risk parameters or the price feed of a listed asset.
+// Disabling an asset only blocks new deposits, existing balances keep counting.
+pub fn update_collateral_asset<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    price_feed: ActorId,
+    collateral_factor: u128,
+    liquidation_threshold: u128,
+    enabled: bool,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    let collateral_asset = match state_mut.collateral_assets.get_mut(&asset) {
+        Some(collateral_asset) => collateral_asset,
+        None => {
+            let error_message = ERROR_COLLATERAL_ASSET_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if !validate_collateral_params(collateral_factor, liquidation_threshold) {
+        let error_message = ERROR_INVALID_COLLATERAL_PARAMS.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    collateral_asset.price_feed = price_feed;
+    collateral_asset.collateral_factor = collateral_factor;
+    collateral_asset.liquidation_threshold = liquidation_threshold;
+    collateral_asset.enabled = enabled;
+
+    service.notify_collateral_asset_updated(asset);
+
+    Ok(())
+}
+
+// Price feed methods
+
+// Set the price of a listed asset. Only its price feed or an admin can do it.
+pub fn set_collateral_price<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    price: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let caller = msg::source();
+    let is_admin = state_mut.admins.contains(&caller);
+
+    let collateral_asset = match state_mut.collateral_assets.get_mut(&asset) {
+        Some(collateral_asset) => collateral_asset,
+        None => {
+            let error_message = ERROR_COLLATERAL_ASSET_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if collateral_asset.price_feed != caller && !is_admin {
+        let error_message = ERROR_INSUFFICIENT_ADMIN_PRIVILEGES.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    collateral_asset.price = price;
+
+    // Revalue the positions backed by the asset so their LTV and risk flag follow the price
+    let holders = state_mut
+        .users
+        .iter()
+        .filter(|(_, user_info)| user_info.collateral_balances.contains_key(&asset))
+        .map(|(user, _)| *user)
+        .collect::<Vec<_>>();
+
+    for user in holders {
+        service.calculate_cv(user);
+        service.update_user_ltv(user);
+        service.calculate_mla(user);
+    }
+
+    service.notify_collateral_price_updated(asset, price);
+
+    Ok(())
+}
+
+// Public methods
+
+// Deposit a listed VFT token as Collateral
+pub async fn deposit_token_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let caller = msg::source();
+
+    let enabled = match state_mut.collateral_assets.get(&asset) {
+        Some(collateral_asset) => collateral_asset.enabled,
+        None => {
+            let error_message = ERROR_COLLATERAL_ASSET_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if !enabled {
+        let error_message = ERROR_COLLATERAL_ASSET_DISABLED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !isolation::can_add_collateral(state_mut, &caller, Some(asset)) {
+        let error_message = ERROR_ISOLATED_COLLATERAL_MIXING.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Transfer tokens from user to contract
+    let result = service.transfer_asset_tokens(asset, caller, exec::program_id(), amount).await;
+
+    if result.is_err() {
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Update user collateral
+    let state_mut = service.state_mut();
+    let current_timestamp = exec::block_timestamp() as u128;
+    let user_info = state_mut.users
+        .entry(caller)
+        .or_insert_with(|| LiquidityInjectionService::<VftClient>::create_new_user(current_timestamp));
+
+    let balance = user_info.collateral_balances.entry(asset).or_insert(0);
+    *balance = balance.saturating_add(amount);
+
+    if let Some(collateral_asset) = state_mut.collateral_assets.get_mut(&asset) {
+        collateral_asset.total_deposited = collateral_asset.total_deposited.saturating_add(amount);
+    }
+
+    //Update CV and MLA
+    service.calculate_cv(caller);
+    service.calculate_mla(caller);
+    service.update_user_ltv(caller);
+
+    LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+
+    service.notify_deposited_token_collateral(asset, amount);
+
+    Ok(())
+}
+
+// Withdraw a VFT token Collateral
+// The remaining collateral must still cover the outstanding loan.
+pub async fn withdraw_token_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Apply accrued interest so the health check uses the real outstanding loan
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    let state_mut = service.state_mut();
+
+    let user_info = match state_mut.users.get_mut(&caller) {
+        Some(user_info) => user_info,
+        None => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    let balance = user_info.collateral_balances.get(&asset).copied().unwrap_or(0);
+
+    if amount == 0 || amount > balance {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: debit collateral BEFORE the external transfer to prevent re-entrancy.
+    LiquidityInjectionService::<VftClient>::debit_collateral_balance(user_info, asset, amount);
+
+    // Reserve, term and stable loans draw on the same collateral
+    let user_info = state_mut.users.get(&caller).unwrap();
+    let max_loan = LiquidityInjectionService::<VftClient>::max_loan_amount(state_mut, user_info);
+    let outstanding = user_info
+        .loan_amount
+        .saturating_add(term_loans::secondary_debt_value(state_mut, &caller));
+
+    if outstanding > max_loan {
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        let balance = user_info.collateral_balances.entry(asset).or_insert(0);
+        *balance = balance.saturating_add(amount);
+        let error_message = ERROR_INSUFFICIENT_COLLATERAL.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if let Some(collateral_asset) = state_mut.collateral_assets.get_mut(&asset) {
+        collateral_asset.total_deposited = collateral_asset.total_deposited.saturating_sub(amount);
+    }
+
+    // Transfer tokens from contract to user
+    let result = service.transfer_asset_tokens(asset, exec::program_id(), caller, amount).await;
+
+    // If transfer fails roll back the state changes to keep accounting consistent
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        let balance = user_info.collateral_balances.entry(asset).or_insert(0);
+        *balance = balance.saturating_add(amount);
+        if let Some(collateral_asset) = state_mut.collateral_assets.get_mut(&asset) {
+            collateral_asset.total_deposited = collateral_asset.total_deposited.saturating_add(amount);
+        }
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Update CV, MLA and LTV after the transfer succeeded
+    service.calculate_cv(caller);
+    service.calculate_mla(caller);
+    service.update_user_ltv(caller);
+
+    let state_mut = service.state_mut();
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+    LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+
+    service.notify_withdrawn_token_collateral(asset, amount);
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use sails_rs::{
+    prelude::*,
+    gstd::msg,
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{term_loans, isolation, dex};
+use crate::states::vstreet_state::VstreetState;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_INVALID_AMOUNT,
+    ERROR_USER_NOT_FOUND,
+    ERROR_COLLATERAL_ASSET_NOT_FOUND,
+    ERROR_COLLATERAL_ASSET_DISABLED,
+    ERROR_COLLATERAL_CAP_EXCEEDED,
+    ERROR_ISOLATED_COLLATERAL_MIXING,
+    ERROR_INVALID_SWAP_PAIR,
+    ERROR_UNHEALTHY_POSITION
+};
+
+// Internal helpers
+
+// `ActorId::zero()` stands for VARA collateral in `swap_collateral`
+fn is_vara(asset: &ActorId) -> bool {
+    *asset == ActorId::zero()
+}
+
+// Move `amount` of a collateral (VARA or a listed token) into or out of the user's position
+fn adjust_collateral(state: &mut VstreetState, user: &ActorId, asset: ActorId, amount: u128, credit: bool) {
+    if amount == 0 {
+        return;
+    }
+
+    let user_info = state.users.get_mut(user).unwrap();
+
+    if is_vara(&asset) {
+        if credit {
+            user_info.balance_vara = user_info.balance_vara.saturating_add(amount);
+            state.total_collateral_vara = state.total_collateral_vara.saturating_add(amount);
+        } else {
+            user_info.balance_vara = user_info.balance_vara.saturating_sub(amount);
+            state.total_collateral_vara = state.total_collateral_vara.saturating_sub(amount);
+        }
+        return;
+    }
+
+    if credit {
+        let balance = user_info.collateral_balances.entry(asset).or_insert(0);
+        *balance = balance.saturating_add(amount);
+    } else {
+        let balance = user_info.collateral_balances.entry(asset).or_insert(0);
+        *balance = balance.saturating_sub(amount);
+        if *balance == 0 {
+            user_info.collateral_balances.remove(&asset);
+        }
+    }
+
+    if let Some(collateral_asset) = state.collateral_assets.get_mut(&asset) {
+        collateral_asset.total_deposited = if credit {
+            collateral_asset.total_deposited.saturating_add(amount)
+        } else {
+            collateral_asset.total_deposited.saturating_sub(amount)
+        };
+    }
+}
+
+fn refresh_position<VftClient>(service: &mut LiquidityInjectionService<VftClient>, user: ActorId)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    service.calculate_cv(user);
+    service.update_user_ltv(user);
+    service.calculate_mla(user);
+
+    let user_info = service.state_mut().users.get_mut(&user).unwrap();
+    LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+}
+
+// Health factor above 1: the debt stays below the liquidation threshold of the collateral
+fn is_healthy(state: &VstreetState, user: &ActorId) -> bool {
+    let user_info = state.users.get(user).unwrap();
+    let debt = user_info
+        .loan_amount
+        .saturating_add(term_loans::secondary_debt_value(state, user));
+
+    debt == 0 || debt.saturating_mul(100) < user_info.cv.saturating_mul(user_info.liquidation_threshold)
+}
+
+// Public methods
+
+// Swap `amount` of the caller's `from_asset` collateral into `to_asset` through the
+// DEX adapter without touching the loan. `ActorId::zero()` stands for VARA.
+// The position is checked as if only `min_out` arrived, so the swap can only fill
+// at or above what was already accepted.
+pub async fn swap_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    from_asset: ActorId,
+    to_asset: ActorId,
+    amount: u128,
+    min_out: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    if amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if from_asset == to_asset {
+        let error_message = ERROR_INVALID_SWAP_PAIR.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let venue = dex::venue(service)?;
+
+    // Apply accrued interest so the health check uses the real outstanding debt
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    let state_mut = service.state_mut();
+
+    let Some(user_info) = state_mut.users.get(&caller) else {
+        let error_message = ERROR_USER_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    };
+
+    if !is_vara(&to_asset) {
+        let enabled = match state_mut.collateral_assets.get(&to_asset) {
+            Some(collateral_asset) => collateral_asset.enabled,
+            None => {
+                let error_message = ERROR_COLLATERAL_ASSET_NOT_FOUND.to_string();
+                service.notify_error(error_message.clone());
+                return Err(error_message);
+            }
+        };
+
+        if !enabled {
+            let error_message = ERROR_COLLATERAL_ASSET_DISABLED.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    } else if state_mut.total_collateral_vara.saturating_add(min_out) > state_mut.config.collateral_cap {
+        let error_message = ERROR_COLLATERAL_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let balance = if is_vara(&from_asset) {
+        user_info.balance_vara
+    } else {
+        user_info.collateral_balances.get(&from_asset).copied().unwrap_or(0)
+    };
+
+    if amount > balance {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: take the collateral out BEFORE it leaves for the venue
+    adjust_collateral(state_mut, &caller, from_asset, amount, false);
+
+    let to_collateral = if is_vara(&to_asset) { None } else { Some(to_asset) };
+    if !isolation::can_add_collateral(state_mut, &caller, to_collateral) {
+        adjust_collateral(state_mut, &caller, from_asset, amount, true);
+        let error_message = ERROR_ISOLATED_COLLATERAL_MIXING.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Count only the guaranteed output until the swap settles
+    adjust_collateral(state_mut, &caller, to_asset, min_out, true);
+    refresh_position(service, caller);
+
+    if !is_healthy(service.state_mut(), &caller) {
+        let state_mut = service.state_mut();
+        adjust_collateral(state_mut, &caller, to_asset, min_out, false);
+        adjust_collateral(state_mut, &caller, from_asset, amount, true);
+        refresh_position(service, caller);
+        let error_message = ERROR_UNHEALTHY_POSITION.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let result = if is_vara(&from_asset) {
+        dex::vara_for_token(service, &venue, to_asset, amount, min_out).await
+    } else if is_vara(&to_asset) {
+        dex::token_for_vara(service, &venue, from_asset, amount, min_out).await
+    } else {
+        dex::token_for_token(service, &venue, from_asset, to_asset, amount, min_out).await
+    };
+
+    let amount_out = match result {
+        Ok(amount_out) => amount_out,
+        Err(error_message) => {
+            let state_mut = service.state_mut();
+            adjust_collateral(state_mut, &caller, to_asset, min_out, false);
+            adjust_collateral(state_mut, &caller, from_asset, amount, true);
+            refresh_position(service, caller);
+            return Err(error_message);
+        }
+    };
+
+    adjust_collateral(service.state_mut(), &caller, to_asset, amount_out.saturating_sub(min_out), true);
+    refresh_position(service, caller);
+    service.refresh_rates();
+
+    service.notify_collateral_swapped(from_asset, to_asset, amount, amount_out);
+
+    // Only a price move while the swap was in flight can get here. The swap has
+    // settled, so the position is kept as is and the caller is told.
+    if !is_healthy(service.state_mut(), &caller) {
+        let error_message = ERROR_UNHEALTHY_POSITION.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use sails_rs::calls::{Call, Action};
+use sails_rs::{
+    prelude::*,
+    gstd::calls::GStdRemoting,
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::clients::swap_venue_client::traits::SwapVenue as _;
+use crate::clients::swap_venue_client::SwapVenue as SwapVenueClient;
+use crate::clients::swap_venue_client::swap_venue::io::{SwapTokenForVara, SwapVaraForToken, SwapTokens};
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::recovery;
+use crate::states::vstreet_state::SwapVenue;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_INVALID_AMOUNT,
+    ERROR_TRANSFER_FAILED,
+    ERROR_SWAP_VENUE_NOT_CONFIGURED,
+    ERROR_SWAP_FAILED
+};
+
+// DEX adapter. Swaps go through the configured venue, so any program that
+// implements the `SwapVenue` interface can be plugged in with `set_swap_venue`.
+
+// Slippage is expressed in basis points
+const BPS: u128 = 10_000;
+
+// Internal helpers
+
+pub(crate) fn min_out(expected: u128, max_slippage: u128) -> u128 {
+    expected.saturating_mul(BPS.saturating_sub(max_slippage)) / BPS
+}
+
+pub(crate) fn venue<VftClient>(service: &mut LiquidityInjectionService<VftClient>) -> Result<SwapVenue, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    match service.state_mut().swap_venue.clone() {
+        Some(venue) => Ok(venue),
+        None => {
+            let error_message = ERROR_SWAP_VENUE_NOT_CONFIGURED.to_string();
+            service.notify_error(error_message.clone());
+            Err(error_message)
+        }
+    }
+}
+
+// Let the venue pull `amount` of `token` from vstreet
+async fn approve<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    token: ActorId,
+    amount: u128,
+) -> bool
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let args = recovery::call_args(service, vec![venue.program], recovery::moved_nothing);
+    let response = service
+        .vft_client
+        .approve(venue.program, U256::from(amount))
+        .with_args(args)
+        .send_recv(token)
+        .await;
+
+    matches!(response, Ok(true))
+}
+
+// Drop the allowance left behind by a failed swap and report the failure
+async fn fail<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    token: ActorId,
+    error_message: String,
+) -> Result<u128, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let _ = approve(service, venue, token, 0).await;
+    service.notify_error(error_message.clone());
+    Err(error_message)
+}
+
+// Sell `amount` of `token` held by vstreet, the VARA comes back with the reply
+pub(crate) async fn token_for_vara<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    token: ActorId,
+    amount: u128,
+    min_out: u128,
+) -> Result<u128, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if !approve(service, venue, token, amount).await {
+        return fail(service, venue, token, ERROR_TRANSFER_FAILED.to_string()).await;
+    }
+
+    let args = recovery::call_args(service, vec![venue.program], recovery::replied::<SwapTokenForVara>);
+    let response = SwapVenueClient::new(GStdRemoting)
+        .swap_token_for_vara(token, amount, min_out)
+        .with_args(args)
+        .send_recv(venue.program)
+        .await;
+
+    match response {
+        Ok(vara) => Ok(vara),
+        Err(_) => fail(service, venue, token, ERROR_SWAP_FAILED.to_string()).await,
+    }
+}
+
+// Sell `amount` VARA held by vstreet for `token`, credited to vstreet.
+// A failed swap bounces the attached VARA back with the reply.
+pub(crate) async fn vara_for_token<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    token: ActorId,
+    amount: u128,
+    min_out: u128,
+) -> Result<u128, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let args = recovery::call_args(service, vec![venue.program], recovery::replied::<SwapVaraForToken>);
+    let response = SwapVenueClient::new(GStdRemoting)
+        .swap_vara_for_token(token, min_out)
+        .with_value(amount)
+        .with_args(args)
+        .send_recv(venue.program)
+        .await;
+
+    response.map_err(|_| {
+        let error_message = ERROR_SWAP_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        error_message
+    })
+}
+
+// Sell `amount` of `token_in` held by vstreet for `token_out`, credited to vstreet
+pub(crate) async fn token_for_token<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    token_in: ActorId,
+    token_out: ActorId,
+    amount: u128,
+    min_out: u128,
+) -> Result<u128, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if !approve(service, venue, token_in, amount).await {
+        return fail(service, venue, token_in, ERROR_TRANSFER_FAILED.to_string()).await;
+    }
+
+    let args = recovery::call_args(service, vec![venue.program], recovery::replied::<SwapTokens>);
+    let response = SwapVenueClient::new(GStdRemoting)
+        .swap_tokens(token_in, token_out, amount, min_out)
+        .with_args(args)
+        .send_recv(venue.program)
+        .await;
+
+    match response {
+        Ok(amount_out) => Ok(amount_out),
+        Err(_) => fail(service, venue, token_in, ERROR_SWAP_FAILED.to_string()).await,
+    }
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+pub fn set_swap_venue<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: ActorId,
+    max_slippage: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if max_slippage >= BPS {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.state_mut().swap_venue = Some(SwapVenue { program: venue, max_slippage });
+
+    service.notify_swap_venue_updated(venue, max_slippage);
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use sails_rs::{
+    prelude::*,
+    gstd::msg,
+};
+use sails_rs::collections::BTreeMap;
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::term_loans;
+use crate::states::vstreet_state::{VstreetState, EModeCategory, CollateralAsset};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_USER_NOT_FOUND,
+    ERROR_INSUFFICIENT_COLLATERAL,
+    ERROR_EMODE_CATEGORY_NOT_FOUND,
+    ERROR_INVALID_EMODE_PARAMS,
+    ERROR_EMODE_DEBT_OUTSIDE_CATEGORY
+};
+
+// Internal helpers
+
+// Collateral factor and liquidation threshold of a token collateral for a user.
+// Assets inside the user's e-mode category use the category parameters instead.
+pub fn effective_collateral_params(
+    emode_categories: &BTreeMap<u8, EModeCategory>,
+    category_id: u8,
+    asset: &ActorId,
+    collateral_asset: &CollateralAsset,
+) -> (u128, u128) {
+    match emode_categories.get(&category_id) {
+        Some(category) if category.assets.contains(asset) => {
+            (category.ltv, category.liquidation_threshold)
+        }
+        _ => (collateral_asset.collateral_factor, collateral_asset.liquidation_threshold),
+    }
+}
+
+// Users in e-mode can only borrow assets of their category
+pub fn can_borrow(state: &VstreetState, user: &ActorId, asset: &ActorId) -> bool {
+    let category_id = state.users.get(user).map(|user_info| user_info.emode_category).unwrap_or(0);
+
+    match state.emode_categories.get(&category_id) {
+        Some(category) => category.assets.contains(asset),
+        None => true,
+    }
+}
+
+// Primary pool loans (variable, stable and term) borrow the primary VFT
+pub fn can_borrow_primary(state: &VstreetState, user: &ActorId) -> bool {
+    state
+        .vft_contract_id
+        .map(|vft_contract_id| can_borrow(state, user, &vft_contract_id))
+        .unwrap_or(true)
+}
+
+fn has_debt_outside(state: &VstreetState, user: &ActorId, category: &EModeCategory) -> bool {
+    let primary_in_category = state
+        .vft_contract_id
+        .map(|vft_contract_id| category.assets.contains(&vft_contract_id))
+        .unwrap_or(false);
+
+    let primary_debt = state
+        .users
+        .get(user)
+        .map(|user_info| user_info.loan_amount.saturating_add(user_info.stable_loan_amount))
+        .unwrap_or(0)
+        .saturating_add(term_loans::term_debt_value(state, user));
+
+    if primary_debt > 0 && !primary_in_category {
+        return true;
+    }
+
+    state.reserves.iter().any(|(asset, reserve)| {
+        !category.assets.contains(asset)
+            && reserve
+                .positions
+                .get(user)
+                .map(|position| position.loan_amount > 0)
+                .unwrap_or(false)
+    })
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// Create or update an e-mode category. Category 0 means no e-mode and cannot be set.
+pub fn set_emode_category<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    category_id: u8,
+    ltv: u128,
+    liquidation_threshold: u128,
+    assets: Vec<ActorId>,
+    label: String,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    if category_id == 0
+        || ltv == 0
+        || ltv > 97
+        || liquidation_threshold < ltv
+        || liquidation_threshold > 100
+        || assets.is_empty()
+    {
+        let error_message = ERROR_INVALID_EMODE_PARAMS.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    state_mut.emode_categories.insert(
+        category_id,
+        EModeCategory {
+            ltv,
+            liquidation_threshold,
+            assets,
+            label,
+        },
+    );
+
+    // Positions already in the category follow the new parameters
+    service.refresh_emode_users(category_id);
+
+    service.notify_emode_category_updated(category_id);
+
+    Ok(())
+}
+
+// Public methods
+
+// Opt into an e-mode category, 0 leaves e-mode. Existing debt must be in the
+// category's assets and the position must stay healthy under the new parameters.
+pub fn set_user_emode<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    category_id: u8,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Apply accrued interest so the health check uses the real outstanding debt
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    let state_mut = service.state_mut();
+
+    if !state_mut.users.contains_key(&caller) {
+        let error_message = ERROR_USER_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if category_id != 0 {
+        let category = match state_mut.emode_categories.get(&category_id) {
+            Some(category) => category,
+            None => {
+                let error_message = ERROR_EMODE_CATEGORY_NOT_FOUND.to_string();
+                service.notify_error(error_message.clone());
+                return Err(error_message);
+            }
+        };
+
+        if has_debt_outside(state_mut, &caller, category) {
+            let error_message = ERROR_EMODE_DEBT_OUTSIDE_CATEGORY.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    }
+
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+    let previous_category = user_info.emode_category;
+    user_info.emode_category = category_id;
+
+    let user_info = state_mut.users.get(&caller).unwrap();
+    let max_loan = LiquidityInjectionService::<VftClient>::max_loan_amount(state_mut, user_info);
+    let outstanding = user_info
+        .loan_amount
+        .saturating_add(term_loans::secondary_debt_value(state_mut, &caller));
+
+    if outstanding > max_loan {
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        user_info.emode_category = previous_category;
+        let error_message = ERROR_INSUFFICIENT_COLLATERAL.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    term_loans::refresh_user_position(service, caller);
+
+    service.notify_user_emode_set(caller, category_id);
+
+    Ok(())
+}
@@ -0,0 +1,522 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::savings;
+use crate::states::vstreet_state::{Governance, Proposal, ProposalStatus, ConfigChange};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT,
+    ERROR_INVALID_LTV,
+    ERROR_COLLATERAL_ASSET_NOT_FOUND,
+    ERROR_GOVERNANCE_NOT_CONFIGURED,
+    ERROR_GOVERNANCE_TOKENS_LOCKED,
+    ERROR_PROPOSAL_NOT_FOUND,
+    ERROR_PROPOSAL_THRESHOLD,
+    ERROR_PROPOSAL_NOT_ACTIVE,
+    ERROR_VOTING_CLOSED,
+    ERROR_VOTING_ACTIVE,
+    ERROR_ALREADY_VOTED,
+    ERROR_NO_VOTING_POWER,
+    ERROR_PROPOSAL_NOT_QUEUED,
+    ERROR_TIMELOCK_NOT_EXPIRED
+};
+
+// Internal helpers
+
+// Locked balance of a holder strictly before `timestamp`, so tokens locked
+// in the same block a proposal is created do not count towards it
+pub fn locked_at(governance: &Governance, holder: &ActorId, timestamp: u128) -> u128 {
+    governance
+        .checkpoints
+        .get(holder)
+        .and_then(|checkpoints| {
+            checkpoints
+                .iter()
+                .rev()
+                .find(|(checkpoint_timestamp, _)| *checkpoint_timestamp < timestamp)
+        })
+        .map(|(_, balance)| *balance)
+        .unwrap_or(0)
+}
+
+pub fn current_locked(governance: &Governance, holder: &ActorId) -> u128 {
+    governance
+        .checkpoints
+        .get(holder)
+        .and_then(|checkpoints| checkpoints.last())
+        .map(|(_, balance)| *balance)
+        .unwrap_or(0)
+}
+
+fn write_checkpoint(governance: &mut Governance, holder: ActorId, balance: u128, timestamp: u128) {
+    let checkpoints = governance.checkpoints.entry(holder).or_default();
+
+    match checkpoints.last_mut() {
+        Some(last) if last.0 == timestamp => last.1 = balance,
+        _ => checkpoints.push((timestamp, balance)),
+    }
+}
+
+fn validate_change<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    change: &ConfigChange,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    let error_message = match change {
+        ConfigChange::Ltv(ltv) if *ltv == 0 || *ltv > 95 => ERROR_INVALID_LTV,
+        ConfigChange::PriceFeed { asset, .. } if !state_mut.collateral_assets.contains_key(asset) => {
+            ERROR_COLLATERAL_ASSET_NOT_FOUND
+        }
+        _ => return Ok(()),
+    };
+
+    let error_message = error_message.to_string();
+    service.notify_error(error_message.clone());
+    Err(error_message)
+}
+
+fn apply_change<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    change: ConfigChange,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    validate_change(service, &change)?;
+
+    let state_mut = service.state_mut();
+
+    match change {
+        ConfigChange::Ltv(ltv) => {
+            state_mut.ltv = ltv;
+            service.update_cv_and_mla_for_all_users();
+        }
+        ConfigChange::InterestRates { base_rate, risk_multiplier } => {
+            // Settle interest at the old rates first
+            let _ = service.calculate_all_loan_interest_rate_amounts();
+            service.update_all_rewards();
+            state_mut.config.base_rate = base_rate;
+            state_mut.config.risk_multiplier = risk_multiplier;
+            service.refresh_rates();
+        }
+        ConfigChange::Caps { supply_cap, borrow_cap, collateral_cap } => {
+            state_mut.config.supply_cap = supply_cap;
+            state_mut.config.borrow_cap = borrow_cap;
+            state_mut.config.collateral_cap = collateral_cap;
+            service.notify_caps_updated(supply_cap, borrow_cap, collateral_cap);
+        }
+        ConfigChange::PriceFeed { asset, price_feed } => {
+            state_mut.collateral_assets.get_mut(&asset).unwrap().price_feed = price_feed;
+            service.notify_collateral_asset_updated(asset);
+        }
+        ConfigChange::SavingsRate(rate) => {
+            savings::set_savings_rate(service, rate)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn queue(proposal: &mut Proposal, timelock_delay: u128, current_timestamp: u128) -> u128 {
+    proposal.status = ProposalStatus::Queued;
+    proposal.eta = current_timestamp.saturating_add(timelock_delay);
+    proposal.eta
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// The governance token can only change while nothing is locked
+pub fn set_governance<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    token: ActorId,
+    voting_period: u128,
+    timelock_delay: u128,
+    proposal_threshold: u128,
+    quorum: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let governance = &mut service.state_mut().governance;
+
+    if governance.token != Some(token) && governance.total_locked > 0 {
+        let error_message = ERROR_GOVERNANCE_TOKENS_LOCKED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    governance.token = Some(token);
+    governance.voting_period = voting_period;
+    governance.timelock_delay = timelock_delay;
+    governance.proposal_threshold = proposal_threshold;
+    governance.quorum = quorum;
+
+    service.notify_governance_updated(token);
+
+    Ok(())
+}
+
+// Timelocked admin path: the change skips the vote but not the delay
+pub fn queue_config_change<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    change: ConfigChange,
+) -> Result<u64, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    validate_change(service, &change)?;
+
+    let current_timestamp = exec::block_timestamp() as u128;
+    let governance = &mut service.state_mut().governance;
+    let proposal_id = governance.next_proposal_id;
+
+    let mut proposal = Proposal {
+        proposer: msg::source(),
+        change,
+        snapshot: current_timestamp,
+        voting_ends: current_timestamp,
+        for_votes: 0,
+        against_votes: 0,
+        voters: Vec::new(),
+        status: ProposalStatus::Active,
+        eta: 0,
+    };
+    let eta = queue(&mut proposal, governance.timelock_delay, current_timestamp);
+
+    governance.proposals.insert(proposal_id, proposal);
+    governance.next_proposal_id += 1;
+
+    service.notify_proposal_queued(proposal_id, eta);
+
+    Ok(proposal_id)
+}
+
+// Admins act as guardians and can cancel anything not yet executed
+pub fn cancel_proposal<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    proposal_id: u64,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let governance = &mut service.state_mut().governance;
+
+    let proposal = match governance.proposals.get_mut(&proposal_id) {
+        Some(proposal) if proposal.status == ProposalStatus::Active || proposal.status == ProposalStatus::Queued => proposal,
+        Some(_) => {
+            let error_message = ERROR_PROPOSAL_NOT_ACTIVE.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+        None => {
+            let error_message = ERROR_PROPOSAL_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    proposal.status = ProposalStatus::Canceled;
+
+    service.notify_proposal_canceled(proposal_id);
+
+    Ok(())
+}
+
+// Public methods
+
+// Lock governance tokens to gain voting power from the next block on
+pub async fn lock_governance_tokens<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    let token = match service.state_mut().governance.token {
+        Some(token) => token,
+        None => {
+            let error_message = ERROR_GOVERNANCE_NOT_CONFIGURED.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if service.transfer_asset_tokens(token, caller, exec::program_id(), amount).await.is_err() {
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let governance = &mut service.state_mut().governance;
+    let balance = current_locked(governance, &caller).saturating_add(amount);
+    write_checkpoint(governance, caller, balance, exec::block_timestamp() as u128);
+    governance.total_locked = governance.total_locked.saturating_add(amount);
+
+    service.notify_governance_tokens_locked(amount);
+
+    Ok(())
+}
+
+// Unlock governance tokens. Votes already cast keep their weight.
+pub async fn unlock_governance_tokens<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+    let current_timestamp = exec::block_timestamp() as u128;
+    let governance = &mut service.state_mut().governance;
+
+    let token = match governance.token {
+        Some(token) => token,
+        None => {
+            let error_message = ERROR_GOVERNANCE_NOT_CONFIGURED.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    let locked = current_locked(governance, &caller);
+
+    if amount == 0 || amount > locked {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: debit the locked balance BEFORE the external transfer
+    write_checkpoint(governance, caller, locked - amount, current_timestamp);
+    governance.total_locked = governance.total_locked.saturating_sub(amount);
+
+    if service.transfer_asset_tokens(token, exec::program_id(), caller, amount).await.is_err() {
+        let governance = &mut service.state_mut().governance;
+        let locked = current_locked(governance, &caller).saturating_add(amount);
+        write_checkpoint(governance, caller, locked, current_timestamp);
+        governance.total_locked = governance.total_locked.saturating_add(amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.notify_governance_tokens_unlocked(amount);
+
+    Ok(())
+}
+
+pub fn propose<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    change: ConfigChange,
+) -> Result<u64, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    if service.state_mut().governance.token.is_none() {
+        let error_message = ERROR_GOVERNANCE_NOT_CONFIGURED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    validate_change(service, &change)?;
+
+    let current_timestamp = exec::block_timestamp() as u128;
+    let governance = &mut service.state_mut().governance;
+
+    if current_locked(governance, &caller) < governance.proposal_threshold.max(1) {
+        let error_message = ERROR_PROPOSAL_THRESHOLD.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let proposal_id = governance.next_proposal_id;
+
+    governance.proposals.insert(
+        proposal_id,
+        Proposal {
+            proposer: caller,
+            change,
+            snapshot: current_timestamp,
+            voting_ends: current_timestamp.saturating_add(governance.voting_period),
+            for_votes: 0,
+            against_votes: 0,
+            voters: Vec::new(),
+            status: ProposalStatus::Active,
+            eta: 0,
+        },
+    );
+    governance.next_proposal_id += 1;
+
+    service.notify_proposal_created(proposal_id, caller);
+
+    Ok(proposal_id)
+}
+
+// Vote with the balance locked at the proposal snapshot
+pub fn vote<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    proposal_id: u64,
+    support: bool,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let voter = msg::source();
+    let current_timestamp = exec::block_timestamp() as u128;
+    let governance = &mut service.state_mut().governance;
+
+    let snapshot = match governance.proposals.get(&proposal_id) {
+        Some(proposal) => proposal.snapshot,
+        None => {
+            let error_message = ERROR_PROPOSAL_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+    let weight = locked_at(governance, &voter, snapshot);
+    let proposal = governance.proposals.get_mut(&proposal_id).unwrap();
+
+    if proposal.status != ProposalStatus::Active || current_timestamp >= proposal.voting_ends {
+        let error_message = ERROR_VOTING_CLOSED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if proposal.voters.contains(&voter) {
+        let error_message = ERROR_ALREADY_VOTED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if weight == 0 {
+        let error_message = ERROR_NO_VOTING_POWER.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    proposal.voters.push(voter);
+    if support {
+        proposal.for_votes = proposal.for_votes.saturating_add(weight);
+    } else {
+        proposal.against_votes = proposal.against_votes.saturating_add(weight);
+    }
+
+    service.notify_vote_cast(proposal_id, voter, support, weight);
+
+    Ok(())
+}
+
+// Once voting ends, a proposal with a majority and quorum enters the timelock
+pub fn queue_proposal<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    proposal_id: u64,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let current_timestamp = exec::block_timestamp() as u128;
+    let governance = &mut service.state_mut().governance;
+    let timelock_delay = governance.timelock_delay;
+    let quorum = governance.quorum;
+
+    let proposal = match governance.proposals.get_mut(&proposal_id) {
+        Some(proposal) => proposal,
+        None => {
+            let error_message = ERROR_PROPOSAL_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if proposal.status != ProposalStatus::Active {
+        let error_message = ERROR_PROPOSAL_NOT_ACTIVE.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if current_timestamp < proposal.voting_ends {
+        let error_message = ERROR_VOTING_ACTIVE.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let passed = proposal.for_votes > proposal.against_votes
+        && proposal.for_votes.saturating_add(proposal.against_votes) >= quorum;
+
+    if !passed {
+        proposal.status = ProposalStatus::Defeated;
+        return Ok(());
+    }
+
+    let eta = queue(proposal, timelock_delay, current_timestamp);
+
+    service.notify_proposal_queued(proposal_id, eta);
+
+    Ok(())
+}
+
+// Anyone can execute a queued change once its timelock has expired
+pub fn execute_proposal<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    proposal_id: u64,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let current_timestamp = exec::block_timestamp() as u128;
+    let governance = &mut service.state_mut().governance;
+
+    let proposal = match governance.proposals.get(&proposal_id) {
+        Some(proposal) => proposal,
+        None => {
+            let error_message = ERROR_PROPOSAL_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if proposal.status != ProposalStatus::Queued {
+        let error_message = ERROR_PROPOSAL_NOT_QUEUED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if current_timestamp < proposal.eta {
+        let error_message = ERROR_TIMELOCK_NOT_EXPIRED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    apply_change(service, proposal.change.clone())?;
+
+    let governance = &mut service.state_mut().governance;
+    governance.proposals.get_mut(&proposal_id).unwrap().status = ProposalStatus::Executed;
+
+    service.notify_proposal_executed(proposal_id);
+
+    Ok(())
+}
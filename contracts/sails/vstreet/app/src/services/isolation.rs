@@ -0,0 +1,135 @@
+use sails_rs::prelude::*;
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::term_loans;
+use crate::states::vstreet_state::{VstreetState, UserInfo};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_COLLATERAL_ASSET_NOT_FOUND
+};
+
+// Internal helpers
+
+// Isolated collateral held by a user, if any. A position never holds more than one.
+pub fn isolated_asset(state: &VstreetState, user_info: &UserInfo) -> Option<ActorId> {
+    user_info
+        .collateral_balances
+        .keys()
+        .find(|asset| {
+            state
+                .collateral_assets
+                .get(*asset)
+                .map(|collateral_asset| collateral_asset.debt_ceiling > 0)
+                .unwrap_or(false)
+        })
+        .copied()
+}
+
+// Debt currently backed by an isolated collateral, across every user holding it
+pub fn isolated_debt(state: &VstreetState, asset: &ActorId) -> u128 {
+    state
+        .users
+        .iter()
+        .filter(|(_, user_info)| user_info.collateral_balances.contains_key(asset))
+        .fold(0, |total, (user, user_info)| {
+            total
+                .saturating_add(user_info.loan_amount)
+                .saturating_add(term_loans::secondary_debt_value(state, user))
+        })
+}
+
+// Room left under the debt ceiling of the user's isolated collateral.
+// `None` when the user is not in isolation mode.
+pub fn remaining_ceiling(state: &VstreetState, user: &ActorId) -> Option<u128> {
+    let user_info = state.users.get(user)?;
+    let asset = isolated_asset(state, user_info)?;
+    let debt_ceiling = state.collateral_assets.get(&asset)?.debt_ceiling;
+
+    Some(debt_ceiling.saturating_sub(isolated_debt(state, &asset)))
+}
+
+// Isolated users can only borrow the assets their collateral allows
+pub fn can_borrow(state: &VstreetState, user: &ActorId, asset: &ActorId) -> bool {
+    let isolated = state
+        .users
+        .get(user)
+        .and_then(|user_info| isolated_asset(state, user_info))
+        .and_then(|isolated| state.collateral_assets.get(&isolated));
+
+    match isolated {
+        Some(collateral_asset) => collateral_asset.isolation_borrowable.contains(asset),
+        None => true,
+    }
+}
+
+pub fn can_borrow_primary(state: &VstreetState, user: &ActorId) -> bool {
+    state
+        .vft_contract_id
+        .map(|vft_contract_id| can_borrow(state, user, &vft_contract_id))
+        .unwrap_or(true)
+}
+
+// Isolated collateral cannot be mixed with any other collateral, VARA included.
+// `asset` is the collateral being added, `None` for VARA.
+pub fn can_add_collateral(state: &VstreetState, user: &ActorId, asset: Option<ActorId>) -> bool {
+    let user_info = match state.users.get(user) {
+        Some(user_info) => user_info,
+        None => return true,
+    };
+
+    if let Some(isolated) = isolated_asset(state, user_info) {
+        return asset == Some(isolated);
+    }
+
+    let adding_isolated = asset
+        .and_then(|asset| state.collateral_assets.get(&asset))
+        .map(|collateral_asset| collateral_asset.debt_ceiling > 0)
+        .unwrap_or(false);
+
+    if !adding_isolated {
+        return true;
+    }
+
+    user_info.balance_vara == 0
+        && user_info
+            .collateral_balances
+            .iter()
+            .all(|(held, balance)| Some(*held) == asset || *balance == 0)
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// List a collateral in isolation with a global debt ceiling and the assets it may borrow.
+// A ceiling of 0 lifts isolation.
+pub fn set_isolation<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    debt_ceiling: u128,
+    borrowable: Vec<ActorId>,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    let collateral_asset = match state_mut.collateral_assets.get_mut(&asset) {
+        Some(collateral_asset) => collateral_asset,
+        None => {
+            let error_message = ERROR_COLLATERAL_ASSET_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    collateral_asset.debt_ceiling = debt_ceiling;
+    collateral_asset.isolation_borrowable = borrowable;
+
+    service.update_cv_and_mla_for_all_users();
+
+    service.notify_collateral_asset_updated(asset);
+
+    Ok(())
+}
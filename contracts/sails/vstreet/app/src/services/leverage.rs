@@ -0,0 +1,397 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{borrow, term_loans, isolation, cdp, dex};
+use crate::states::vstreet_state::{VstreetState, SwapVenue};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_USER_NOT_FOUND,
+    ERROR_ISOLATED_COLLATERAL_MIXING,
+    ERROR_INVALID_TARGET_LTV
+};
+
+// Internal helpers
+
+// Debt counted against the position and its collateral value, both in stable units
+fn debt_and_cv(state: &VstreetState, user: &ActorId) -> (u128, u128) {
+    let user_info = state.users.get(user).unwrap();
+    let debt = user_info
+        .loan_amount
+        .saturating_add(term_loans::secondary_debt_value(state, user));
+
+    (debt, user_info.cv)
+}
+
+// Stable to borrow so that debt / cv reaches `target_ltv`, assuming the swap
+// fills at the oracle price. Capped at what the position can still borrow.
+fn leverage_step(state: &VstreetState, user: &ActorId, target_ltv: u128) -> u128 {
+    let (debt, cv) = debt_and_cv(state, user);
+    let target = cv.saturating_mul(target_ltv);
+    let current = debt.saturating_mul(100);
+
+    if target <= current {
+        return 0;
+    }
+
+    let needed = (target - current) / (100 - target_ltv);
+    let user_info = state.users.get(user).unwrap();
+
+    // `take_loan` holds the loan after borrowing against the remaining MLA
+    let borrowable = user_info.mla.saturating_sub(user_info.loan_amount);
+
+    needed.min(borrowable).min(state.config.max_loan_amount)
+}
+
+// VARA to sell so that debt / cv comes back down to `target_ltv`. Only the
+// variable loan is repaid, and no more VARA is released than keeps the
+// position under the market LTV while the swap is in flight.
+fn deleverage_step(state: &VstreetState, user: &ActorId, target_ltv: u128) -> u128 {
+    let (debt, cv) = debt_and_cv(state, user);
+    let target = cv.saturating_mul(target_ltv);
+    let current = debt.saturating_mul(100);
+
+    if current <= target {
+        return 0;
+    }
+
+    let user_info = state.users.get(user).unwrap();
+    let repay = ((current - target) / (100 - target_ltv)).min(user_info.loan_amount);
+
+    let required_cv = current
+        .saturating_add(state.ltv - 1)
+        .checked_div(state.ltv)
+        .unwrap_or(cv);
+    let releasable = cv.saturating_sub(required_cv).min(repay);
+
+    releasable
+        .saturating_mul(state.config.one_tvara)
+        .checked_div(state.config.vara_price)
+        .unwrap_or(0)
+        .min(user_info.balance_vara)
+}
+
+// Recompute the position after its collateral or debt moved
+fn refresh_position<VftClient>(service: &mut LiquidityInjectionService<VftClient>, user: ActorId)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    service.calculate_cv(user);
+    service.update_user_ltv(user);
+    service.calculate_mla(user);
+
+    let user_info = service.state_mut().users.get_mut(&user).unwrap();
+    LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+
+    service.refresh_rates();
+}
+
+// Add (or remove, when `credit` is false) `amount` VARA to the user's collateral
+pub(crate) fn move_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    user: ActorId,
+    amount: u128,
+    credit: bool,
+)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let user_info = state_mut.users.get_mut(&user).unwrap();
+
+    if credit {
+        user_info.balance_vara = user_info.balance_vara.saturating_add(amount);
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(amount);
+    } else {
+        user_info.balance_vara = user_info.balance_vara.saturating_sub(amount);
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_sub(amount);
+    }
+
+    refresh_position(service, user);
+}
+
+// Reduce (or restore, when `repay` is false) the user's variable debt by `amount`
+pub(crate) fn move_debt<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    user: ActorId,
+    amount: u128,
+    repay: bool,
+)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let has_secondary_debt = term_loans::secondary_debt_value(state_mut, &user) > 0;
+    let decimals_factor = state_mut.config.decimals_factor;
+    let user_info = state_mut.users.get_mut(&user).unwrap();
+
+    if repay {
+        user_info.loan_amount = user_info.loan_amount.saturating_sub(amount);
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(amount);
+    } else {
+        user_info.loan_amount = user_info.loan_amount.saturating_add(amount);
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(amount);
+    }
+
+    user_info.loan_amount_usdc = user_info.loan_amount / decimals_factor;
+    user_info.is_loan_active = user_info.loan_amount > 0 || has_secondary_debt;
+
+    refresh_position(service, user);
+}
+
+// Pay out primary tokens vstreet holds for `user`. When the transfer fails they
+// stay with vstreet as the user's stable collateral, withdrawable later, instead
+// of being lost.
+pub(crate) async fn pay_out<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    user: ActorId,
+    amount: u128,
+)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if amount == 0 || service.transfer_tokens(exec::program_id(), user, amount).await.is_ok() {
+        return;
+    }
+
+    let state_mut = service.state_mut();
+    let user_info = state_mut.users.get_mut(&user).unwrap();
+
+    user_info.stable_collateral = user_info.stable_collateral.saturating_add(amount);
+    state_mut.total_stable_collateral = state_mut.total_stable_collateral.saturating_add(amount);
+}
+
+// Hand `amount` freshly borrowed stable to the venue and return the VARA it bought.
+// On failure the stable is back in vstreet's hands (burned again in CDP mode).
+async fn buy_vara<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    stable: ActorId,
+    amount: u128,
+) -> Result<u128, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_mut();
+    let expected = amount
+        .saturating_mul(state.config.one_tvara)
+        .checked_div(state.config.vara_price)
+        .unwrap_or(0);
+    let is_cdp = state.cdp.is_some();
+
+    // Vaults mint the borrowed stable to vstreet first, the venue pulls it from there
+    if is_cdp {
+        cdp::mint_stable(service, exec::program_id(), amount).await?;
+    }
+
+    let bought = dex::token_for_vara(service, venue, stable, amount, dex::min_out(expected, venue.max_slippage)).await;
+
+    if bought.is_err() && is_cdp {
+        let _ = cdp::burn_stable(service, exec::program_id(), amount).await;
+    }
+
+    bought
+}
+
+// Sell `amount` VARA at the venue and return the stable it bought, now held by vstreet
+pub(crate) async fn sell_vara<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    venue: &SwapVenue,
+    stable: ActorId,
+    amount: u128,
+) -> Result<u128, String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_mut();
+    let expected = amount
+        .saturating_mul(state.config.vara_price)
+        .checked_div(state.config.one_tvara)
+        .unwrap_or(0);
+
+    dex::vara_for_token(service, venue, stable, amount, dex::min_out(expected, venue.max_slippage)).await
+}
+
+fn validate<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    caller: ActorId,
+    target_ltv: u128,
+) -> Result<(SwapVenue, ActorId), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let venue = dex::venue(service)?;
+    let state = service.state_mut();
+
+    if target_ltv >= state.ltv {
+        let error_message = ERROR_INVALID_TARGET_LTV.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !state.users.contains_key(&caller) {
+        let error_message = ERROR_USER_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let stable = state.vft_contract_id.ok_or_else(|| {
+        "VFT contract ID not configured".to_string()
+    })?;
+
+    Ok((venue, stable))
+}
+
+// Public methods
+
+// Borrow the primary stable, swap it to VARA at the venue and re-deposit it as
+// collateral, up to `max_iterations` times or until the position reaches
+// `target_ltv`. Every iteration leaves a healthy position, so a failing
+// iteration is undone on its own and the completed ones are kept.
+pub async fn leverage<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    target_ltv: u128,
+    max_iterations: u32,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    if target_ltv == 0 || max_iterations == 0 {
+        let error_message = ERROR_INVALID_TARGET_LTV.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let (venue, stable) = validate(service, caller, target_ltv)?;
+
+    if !isolation::can_add_collateral(service.state_mut(), &caller, None) {
+        let error_message = ERROR_ISOLATED_COLLATERAL_MIXING.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let mut iterations = 0;
+    let mut borrowed = 0u128;
+    let mut collateral_added = 0u128;
+
+    while iterations < max_iterations {
+        let _ = service.calculate_loan_interest_rate_amount(caller);
+        refresh_position(service, caller);
+
+        let state = service.state_mut();
+        let amount = leverage_step(state, &caller, target_ltv);
+        let expected_vara = amount
+            .saturating_mul(state.config.one_tvara)
+            .checked_div(state.config.vara_price)
+            .unwrap_or(0);
+
+        if expected_vara == 0 || state.total_collateral_vara.saturating_add(expected_vara) > state.config.collateral_cap {
+            break;
+        }
+
+        // Borrow through the regular path so every borrow check applies. The
+        // transfer is deferred and dropped: the tokens go to the venue instead.
+        service.begin_settlement();
+        let result = borrow::take_loan(service, amount).await;
+        let _ = service.take_settlement();
+        result?;
+
+        let vara = match buy_vara(service, &venue, stable, amount).await {
+            Ok(vara) => vara,
+            Err(error_message) => {
+                move_debt(service, caller, amount, true);
+                return Err(error_message);
+            }
+        };
+
+        move_collateral(service, caller, vara, true);
+
+        iterations += 1;
+        borrowed = borrowed.saturating_add(amount);
+        collateral_added = collateral_added.saturating_add(vara);
+    }
+
+    service.notify_leveraged(iterations, borrowed, collateral_added);
+
+    Ok(())
+}
+
+// Inverse of `leverage`: withdraw VARA collateral, swap it to the primary stable
+// and repay the variable loan until the position is back down to `target_ltv`
+pub async fn deleverage<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    target_ltv: u128,
+    max_iterations: u32,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    if max_iterations == 0 {
+        let error_message = ERROR_INVALID_TARGET_LTV.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let (venue, stable) = validate(service, caller, target_ltv)?;
+
+    let mut iterations = 0;
+    let mut collateral_sold = 0u128;
+    let mut debt_repaid = 0u128;
+
+    while iterations < max_iterations {
+        let _ = service.calculate_loan_interest_rate_amount(caller);
+        refresh_position(service, caller);
+
+        let amount = deleverage_step(service.state_mut(), &caller, target_ltv);
+
+        if amount == 0 {
+            break;
+        }
+
+        // CEI: release the collateral BEFORE it leaves for the venue
+        move_collateral(service, caller, amount, false);
+
+        let proceeds = match sell_vara(service, &venue, stable, amount).await {
+            Ok(proceeds) => proceeds,
+            Err(error_message) => {
+                move_collateral(service, caller, amount, true);
+                return Err(error_message);
+            }
+        };
+
+        let loan_amount = service.state_mut().users.get(&caller).unwrap().loan_amount;
+        let repaid = proceeds.min(loan_amount);
+        move_debt(service, caller, repaid, true);
+
+        // Vault debt is burned, pool debt simply stays in the pool
+        if service.state_mut().cdp.is_some() && repaid > 0 {
+            if let Err(error_message) = cdp::burn_stable(service, exec::program_id(), repaid).await {
+                move_debt(service, caller, repaid, false);
+                pay_out(service, caller, proceeds).await;
+                return Err(error_message);
+            }
+        }
+
+        // Anything the venue paid above the debt belongs to the user
+        pay_out(service, caller, proceeds - repaid).await;
+
+        iterations += 1;
+        collateral_sold = collateral_sold.saturating_add(amount);
+        debt_repaid = debt_repaid.saturating_add(repaid);
+    }
+
+    service.notify_deleveraged(iterations, collateral_sold, debt_repaid);
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{exec, msg},
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{recovery, supply};
+use crate::states::vstreet_state::VstreetState;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_OPERATION_IN_PROGRESS,
+    ERROR_PROTOCOL_BUSY
+};
+
+// In-flight locks. CEI keeps a single message consistent, but every await hands
+// control back to the queue, so a second message could land between a debit and
+// its rollback. Operations that await hold a lock on the positions they touch.
+
+// Locks never expire while the message holding them is alive, however long its
+// replies take. A transfer that gets no reply times out and takes the regular
+// release path; a message that traps or leaves the waitlist after an await has
+// its locks released from `handle_signal`, see `recovery`.
+
+// Internal helpers
+
+fn fail<VftClient>(service: &mut LiquidityInjectionService<VftClient>, error: &str) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let error_message = error.to_string();
+    service.notify_error(error_message.clone());
+    Err(error_message)
+}
+
+pub(crate) fn is_locked(state: &VstreetState, user: &ActorId) -> bool {
+    state.locks.users.contains_key(user)
+}
+
+fn is_globally_locked(state: &VstreetState) -> bool {
+    state.locks.global.is_some()
+}
+
+// Reject the call if any of `users` has an operation in flight or a batch job runs
+pub(crate) fn ensure_idle<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    users: &[ActorId],
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_mut();
+
+    if is_globally_locked(state) {
+        return fail(service, ERROR_PROTOCOL_BUSY);
+    }
+
+    if users.iter().any(|user| is_locked(state, user)) {
+        return fail(service, ERROR_OPERATION_IN_PROGRESS);
+    }
+
+    Ok(())
+}
+
+// Lock `users` until `release` is called with the same list. The running message
+// is tracked as a pending operation meanwhile, see `recovery`.
+pub(crate) fn acquire<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    users: &[ActorId],
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    ensure_idle(service, users)?;
+    recovery::begin(service, users)?;
+
+    let block_height = exec::block_height();
+    let state_mut = service.state_mut();
+    for user in users {
+        state_mut.locks.users.insert(*user, block_height);
+    }
+
+    Ok(())
+}
+
+// `acquire` for calls that take value: a rejected call hands the value back
+pub(crate) fn acquire_payable<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    users: &[ActorId],
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let result = acquire(service, users);
+
+    if let Err(error_message) = &result {
+        supply::refund_value(msg::source(), msg::value(), error_message);
+    }
+
+    result
+}
+
+pub(crate) fn release<VftClient>(service: &mut LiquidityInjectionService<VftClient>, users: &[ActorId])
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    for user in users {
+        state_mut.locks.users.remove(user);
+    }
+
+    recovery::finish(service);
+}
+
+// Batch jobs only exclude each other and new user operations. They do not wait
+// for operations already in flight, so a price update can never be starved;
+// they skip locked positions instead.
+pub(crate) fn acquire_global<VftClient>(service: &mut LiquidityInjectionService<VftClient>) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if is_globally_locked(service.state_mut()) {
+        return fail(service, ERROR_PROTOCOL_BUSY);
+    }
+
+    recovery::begin_global(service)?;
+    service.state_mut().locks.global = Some(exec::block_height());
+
+    Ok(())
+}
+
+pub(crate) fn release_global<VftClient>(service: &mut LiquidityInjectionService<VftClient>)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    service.state_mut().locks.global = None;
+    recovery::finish_global();
+}
@@ -1,4 +1,23 @@
 pub mod vst_liquidity_injection;
 pub mod supply;
 pub mod borrow;
+pub mod collateral;
+pub mod reserves;
+pub mod vara_pool;
+pub mod term_loans;
+pub mod stable_rate;
+pub mod emode;
+pub mod isolation;
+pub mod cdp;
+pub mod savings;
+pub mod governance;
+pub mod multicall;
+pub mod positions;
+pub mod leverage;
+pub mod dex;
+pub mod collateral_swap;
+pub mod risk;
+pub mod preview;
+pub mod locks;
+pub mod recovery;
 pub mod utils;
\ No newline at end of file
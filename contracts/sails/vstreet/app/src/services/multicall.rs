@@ -0,0 +1,289 @@
+use sails_rs::{
+    prelude::*,
+    gstd::msg,
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::{LiquidityInjectionService, LiquidityEvent};
+use crate::services::{supply, borrow, cdp, term_loans};
+use crate::states::vstreet_state::{VstreetState, UserInfo, Action};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_INVALID_AMOUNT,
+    ERROR_TRANSFER_FAILED
+};
+
+// Token flows of a multicall, netted and settled once all actions succeeded
+#[derive(Default)]
+pub struct Settlement {
+    // Primary VFT owed by the caller
+    pub inbound: u128,
+    // Primary VFT owed to the caller
+    pub outbound: u128,
+    // VARA owed to the caller
+    pub vara_out: u128,
+}
+
+// Internal helpers
+
+async fn run_action<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    caller: ActorId,
+    action: Action,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    match action {
+        Action::DepositLiquidity(amount) => supply::deposit_liquidity(service, amount).await,
+        Action::WithdrawLiquidity(amount) => supply::withdraw_liquidity(service, amount).await,
+        Action::WithdrawRewards => supply::withdraw_rewards(service).await,
+        Action::DepositCollateral(value) => supply::credit_vara_collateral(service, caller, caller, value),
+        Action::WithdrawCollateral(amount) => supply::withdraw_collateral(service, amount).await,
+        Action::TakeLoan(amount) => borrow::take_loan(service, amount).await,
+        Action::PayLoan(amount) => borrow::pay_loan(service, amount).await,
+        Action::PayAllLoan => borrow::pay_all_loan(service).await,
+    }
+}
+
+// Pool totals a multicall can move
+struct Totals {
+    total_deposited: u128,
+    total_borrowed: u128,
+    total_collateral_vara: u128,
+    available_rewards_pool: u128,
+    total_rewards_distributed: u128,
+}
+
+impl Totals {
+    fn of(state: &VstreetState) -> Self {
+        Self {
+            total_deposited: state.total_deposited,
+            total_borrowed: state.total_borrowed,
+            total_collateral_vara: state.total_collateral_vara,
+            available_rewards_pool: state.available_rewards_pool,
+            total_rewards_distributed: state.total_rewards_distributed,
+        }
+    }
+}
+
+// The caller's position and the pool totals before a batch. Batch actions
+// only touch the caller, so this is all that has to be put back on failure.
+pub(crate) struct Snapshot {
+    user: Option<UserInfo>,
+    totals: Totals,
+}
+
+impl Snapshot {
+    pub(crate) fn take(state: &VstreetState, caller: ActorId) -> Self {
+        Self {
+            user: state.users.get(&caller).cloned(),
+            totals: Totals::of(state),
+        }
+    }
+
+    fn restore_user(&self, state: &mut VstreetState, caller: ActorId) {
+        match &self.user {
+            Some(user_info) => {
+                state.users.insert(caller, user_info.clone());
+            }
+            None => {
+                state.users.remove(&caller);
+            }
+        }
+    }
+}
+
+fn revert_total(current: u128, before: u128, after: u128) -> u128 {
+    current.saturating_add(before).saturating_sub(after)
+}
+
+// Undo the caller's side of the batch once the settlement has failed.
+// Other messages may have run during the settlement await, so the pool totals
+// are moved back by what the actions changed instead of being overwritten.
+fn revert_caller<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    snapshot: &Snapshot,
+    after: &Totals,
+    caller: ActorId,
+)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let before = &snapshot.totals;
+    let state_mut = service.state_mut();
+
+    snapshot.restore_user(state_mut, caller);
+
+    state_mut.total_deposited = revert_total(state_mut.total_deposited, before.total_deposited, after.total_deposited);
+    state_mut.total_borrowed = revert_total(state_mut.total_borrowed, before.total_borrowed, after.total_borrowed);
+    state_mut.total_collateral_vara = revert_total(
+        state_mut.total_collateral_vara,
+        before.total_collateral_vara,
+        after.total_collateral_vara,
+    );
+    state_mut.available_rewards_pool = revert_total(
+        state_mut.available_rewards_pool,
+        before.available_rewards_pool,
+        after.available_rewards_pool,
+    );
+    state_mut.total_rewards_distributed = revert_total(
+        state_mut.total_rewards_distributed,
+        before.total_rewards_distributed,
+        after.total_rewards_distributed,
+    );
+
+    service.refresh_rates();
+}
+
+// Move the net primary VFT flow of a settlement: collected from the caller
+// when `inbound` is larger, handed out when `outbound` is
+async fn move_primary<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    caller: ActorId,
+    inbound: u128,
+    outbound: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if inbound > outbound {
+        cdp::collect(service, caller, inbound - outbound).await
+    } else if outbound > inbound {
+        cdp::disburse(service, caller, outbound - inbound).await
+    } else {
+        Ok(())
+    }
+}
+
+// Drop the deferred flows of a failed batch. Nothing has been awaited yet,
+// so the caller and the totals are put back exactly as they were.
+pub(crate) fn abort<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    snapshot: Snapshot,
+    caller: ActorId,
+    attached: u128,
+    error_message: &str,
+)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let _ = service.take_settlement();
+
+    let state_mut = service.state_mut();
+    let totals = &snapshot.totals;
+
+    snapshot.restore_user(state_mut, caller);
+    state_mut.total_deposited = totals.total_deposited;
+    state_mut.total_borrowed = totals.total_borrowed;
+    state_mut.total_collateral_vara = totals.total_collateral_vara;
+    state_mut.available_rewards_pool = totals.available_rewards_pool;
+    state_mut.total_rewards_distributed = totals.total_rewards_distributed;
+
+    service.refresh_rates();
+    supply::refund_value(caller, attached, error_message);
+}
+
+// Settle the deferred flows of a batch: the net primary VFT flow with a single
+// transfer, mint or burn, then the VARA owed to the caller
+pub(crate) async fn settle<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    snapshot: &Snapshot,
+    caller: ActorId,
+    attached: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let settlement = service.take_settlement();
+    let after = Totals::of(service.state_mut());
+
+    if let Err(error_message) = move_primary(service, caller, settlement.inbound, settlement.outbound).await {
+        revert_caller(service, snapshot, &after, caller);
+        supply::refund_value(caller, attached, &error_message);
+        return Err(error_message);
+    }
+
+    // VARA leaves last, once the token leg can no longer fail
+    if settlement.vara_out == 0 {
+        return Ok(());
+    }
+
+    let sent = msg::send(
+        caller,
+        LiquidityEvent::WithdrawnVara { amount: settlement.vara_out / service.state_mut().config.one_tvara },
+        settlement.vara_out,
+    );
+
+    if sent.is_ok() {
+        return Ok(());
+    }
+
+    let error_message = ERROR_TRANSFER_FAILED.to_string();
+    service.notify_error(error_message.clone());
+
+    // Hand the token leg back the opposite way before reverting the caller
+    let unwound = move_primary(service, caller, settlement.outbound, settlement.inbound).await;
+
+    if unwound.is_ok() {
+        revert_caller(service, snapshot, &after, caller);
+    } else {
+        // The tokens stay moved, so the VARA is kept as the caller's collateral
+        let state_mut = service.state_mut();
+        if let Some(user_info) = state_mut.users.get_mut(&caller) {
+            user_info.balance_vara = user_info.balance_vara.saturating_add(settlement.vara_out);
+        }
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(settlement.vara_out);
+        term_loans::refresh_user_position(service, caller);
+    }
+
+    supply::refund_value(caller, attached, &error_message);
+    Err(error_message)
+}
+
+// Public methods
+
+// Execute `actions` in order for the caller. The value attached must match the
+// VARA deposited by `DepositCollateral` actions.
+pub async fn multicall<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    actions: Vec<Action>,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+    let attached = msg::value();
+    let action_count = actions.len() as u32;
+
+    let vara_in = actions.iter().fold(0u128, |total, action| match action {
+        Action::DepositCollateral(value) => total.saturating_add(*value),
+        _ => total,
+    });
+
+    if actions.is_empty() || vara_in != attached {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        supply::refund_value(caller, attached, &error_message);
+        return Err(error_message);
+    }
+
+    // Token flows are deferred, so no action awaits another program and the
+    // snapshot cannot be overtaken by other messages before settlement
+    let snapshot = Snapshot::take(service.state_mut(), caller);
+    service.begin_settlement();
+
+    for action in actions {
+        if let Err(error_message) = run_action(service, caller, action).await {
+            abort(service, snapshot, caller, attached, &error_message);
+            return Err(error_message);
+        }
+    }
+
+    settle(service, &snapshot, caller, attached).await?;
+
+    service.notify_multicall_executed(action_count);
+
+    Ok(())
+}
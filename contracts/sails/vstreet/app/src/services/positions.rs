@@ -0,0 +1,196 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::{LiquidityInjectionService, LiquidityEvent};
+use crate::services::{supply, borrow, term_loans, multicall, leverage, dex, cdp};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_USER_NOT_FOUND,
+    ERROR_SECONDARY_DEBT_OUTSTANDING,
+    ERROR_INVALID_AMOUNT,
+    ERROR_INSUFFICIENT_COLLATERAL,
+    ERROR_TRANSFER_FAILED
+};
+
+// Public methods
+
+// Repay the full variable debt, claim rewards and return all VARA collateral
+// in one message. Runs as a batch, so either everything happens or nothing does.
+pub async fn close_position<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Accrue interest up to now so no dust debt is left behind
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+    service.update_all_rewards();
+
+    let state_mut = service.state_mut();
+
+    if !state_mut.users.contains_key(&caller) {
+        let error_message = ERROR_USER_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Reserve, term and stable loans keep the collateral locked
+    if term_loans::secondary_debt_value(state_mut, &caller) > 0 {
+        let error_message = ERROR_SECONDARY_DEBT_OUTSTANDING.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let user_info = state_mut.users.get(&caller).unwrap();
+    let debt_repaid = user_info.loan_amount;
+    let rewards = user_info.rewards;
+    let claim_rewards = rewards > 0
+        && rewards >= state_mut.config.min_rewards_withdraw
+        && rewards <= state_mut.available_rewards_pool;
+
+    let snapshot = multicall::Snapshot::take(state_mut, caller);
+    service.begin_settlement();
+
+    if debt_repaid > 0 {
+        if let Err(error_message) = borrow::pay_all_loan(service).await {
+            multicall::abort(service, snapshot, caller, 0, &error_message);
+            return Err(error_message);
+        }
+    }
+
+    let rewards_claimed = if claim_rewards {
+        if let Err(error_message) = supply::withdraw_rewards(service).await {
+            multicall::abort(service, snapshot, caller, 0, &error_message);
+            return Err(error_message);
+        }
+        rewards
+    } else {
+        0
+    };
+
+    // The whole VARA balance is released, including amounts that are not a
+    // multiple of one TVARA and cannot go through `withdraw_collateral`
+    let state_mut = service.state_mut();
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+    let collateral_returned = user_info.balance_vara;
+
+    user_info.balance_vara = 0;
+    state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_sub(collateral_returned);
+    service.defer_vara(collateral_returned);
+
+    term_loans::refresh_user_position(service, caller);
+    service.refresh_rates();
+
+    multicall::settle(service, &snapshot, caller, 0).await?;
+
+    service.notify_position_closed(debt_repaid, collateral_returned, rewards_claimed);
+
+    Ok(())
+}
+
+// Repay `amount` of the caller's variable loan with their own VARA collateral,
+// sold at the swap venue for the primary token. Only `self_liquidation_fee` is
+// charged on top, instead of the collateral share seized by a liquidation. The
+// debt goes down by what the swap actually paid, capped at `amount`.
+pub async fn self_liquidate<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    let state_mut = service.state_mut();
+    let owner = state_mut.owner;
+
+    let user_info = match state_mut.users.get(&caller) {
+        Some(user_info) => user_info,
+        None => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if amount == 0 || amount > user_info.loan_amount {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let collateral_sold = amount
+        .saturating_mul(state_mut.config.one_tvara)
+        .checked_div(state_mut.config.vara_price)
+        .unwrap_or(0);
+    let fee = collateral_sold.saturating_mul(state_mut.config.self_liquidation_fee) / 100;
+    let seized = collateral_sold.saturating_add(fee);
+
+    if collateral_sold == 0 || seized > user_info.balance_vara {
+        let error_message = ERROR_INSUFFICIENT_COLLATERAL.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let venue = dex::venue(service)?;
+    let stable = service.state_mut().vft_contract_id.ok_or_else(|| {
+        "VFT contract ID not configured".to_string()
+    })?;
+
+    // CEI: take the collateral BEFORE it leaves for the venue
+    leverage::move_collateral(service, caller, seized, false);
+
+    let proceeds = match leverage::sell_vara(service, &venue, stable, collateral_sold).await {
+        Ok(proceeds) => proceeds,
+        Err(error_message) => {
+            leverage::move_collateral(service, caller, seized, true);
+            return Err(error_message);
+        }
+    };
+
+    let repaid = proceeds.min(amount);
+    leverage::move_debt(service, caller, repaid, true);
+
+    // Vault debt is burned, pool debt is paid back with the proceeds
+    if service.state_mut().cdp.is_some() {
+        if let Err(error_message) = cdp::burn_stable(service, exec::program_id(), repaid).await {
+            leverage::move_debt(service, caller, repaid, false);
+            leverage::pay_out(service, caller, proceeds).await;
+            leverage::move_collateral(service, caller, fee, true);
+            return Err(error_message);
+        }
+    }
+
+    // Anything the venue paid above `amount` belongs to the user
+    leverage::pay_out(service, caller, proceeds - repaid).await;
+
+    let sent = fee == 0
+        || msg::send(
+            owner,
+            LiquidityEvent::SelfLiquidated { user: caller, debt_repaid: repaid, collateral_sold, fee },
+            fee,
+        )
+        .is_ok();
+
+    // The collateral is sold already, an undelivered fee goes back to the user
+    if !sent {
+        leverage::move_collateral(service, caller, fee, true);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message);
+    }
+
+    service.notify_self_liquidated(caller, repaid, collateral_sold, if sent { fee } else { 0 });
+
+    Ok(())
+}
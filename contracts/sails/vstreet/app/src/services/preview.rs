@@ -0,0 +1,250 @@
+use sails_rs::{
+    prelude::*,
+    gstd::exec,
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{term_loans, emode, isolation};
+use crate::states::vstreet_state::{VstreetState, UserInfo};
+
+// Read-only previews. They run the same math as `calculate_cv`, `calculate_mla`
+// and `update_user_ltv` on a copy of the position, so nothing is stored.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionPreview {
+    pub cv: u128,
+    pub mla: u128,
+    pub ltv: u128,
+    pub liquidation_threshold: u128,
+    pub available_to_withdraw_vara: u128,
+    // VARA price at which the position becomes liquidatable, 0 when no price does
+    pub liquidation_price: u128,
+    pub loan_amount: u128,
+    // Whether the previewed action would pass the same checks as the command
+    pub allowed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositPreview {
+    pub total_deposited: u128,
+    pub utilization_factor: u128,
+    pub apr: u128,
+    pub interest_rate: u128,
+    pub allowed: bool,
+}
+
+// Internal helpers
+
+// The loan with interest accrued up to now, as `calculate_loan_interest_rate_amount` would apply it
+fn current_loan_amount<VftClient: Vft<Args = GStdArgs>>(state: &VstreetState, user_info: &UserInfo) -> u128 {
+    let interest_rate = match state.cdp.as_ref() {
+        Some(cdp) => cdp.stability_fee,
+        None => {
+            let utilization_factor = LiquidityInjectionService::<VftClient>::utilization_factor_for(
+                state.total_deposited,
+                state.total_borrowed,
+                state.config.decimals_factor,
+            );
+            LiquidityInjectionService::<VftClient>::apr_for(&state.config, utilization_factor).saturating_add(state.config.dev_fee)
+        }
+    };
+
+    let current_timestamp = exec::block_timestamp() as u128;
+    let time_diff_seconds = current_timestamp.saturating_sub(user_info.borrow_last_updated) / 1000;
+
+    user_info.loan_amount.saturating_add(LiquidityInjectionService::<VftClient>::accrued_interest(
+        &state.config,
+        user_info.loan_amount,
+        interest_rate,
+        time_diff_seconds,
+    ))
+}
+
+// VARA price at which the LTV reaches the liquidation threshold. Token collaterals
+// keep their current price, so only the VARA part of the CV moves.
+fn vara_liquidation_price<VftClient: Vft<Args = GStdArgs>>(state: &VstreetState, user_info: &UserInfo, debt: u128) -> u128 {
+    if debt == 0 || user_info.balance_vara == 0 {
+        return 0;
+    }
+
+    let mut tokens_only = user_info.clone();
+    tokens_only.balance_vara = 0;
+    let (_, token_weighted_threshold) = LiquidityInjectionService::<VftClient>::collateral_value(state, &tokens_only);
+
+    // Liquidation starts once debt * 100 >= sum of collateral values * their thresholds
+    let shortfall = debt.saturating_mul(100).saturating_sub(token_weighted_threshold);
+
+    shortfall
+        .saturating_mul(state.config.one_tvara)
+        .checked_div(user_info.balance_vara.saturating_mul(state.ltv))
+        .unwrap_or(0)
+}
+
+// Metrics of `user_info` as if it belonged to `user`, with `extra_debt` of the
+// isolation ceiling already used by the previewed action
+fn position_metrics<VftClient: Vft<Args = GStdArgs>>(
+    state: &VstreetState,
+    user: &ActorId,
+    user_info: &UserInfo,
+    extra_debt: u128,
+) -> PositionPreview {
+    let (cv, weighted_threshold) = LiquidityInjectionService::<VftClient>::collateral_value(state, user_info);
+    let liquidation_threshold = weighted_threshold.checked_div(cv).unwrap_or(state.ltv);
+
+    let debt = user_info
+        .loan_amount
+        .saturating_add(term_loans::secondary_debt_value(state, user));
+
+    let mut mla = LiquidityInjectionService::<VftClient>::max_loan_amount(state, user_info).saturating_sub(debt);
+    if let Some(ceiling_left) = isolation::remaining_ceiling(state, user) {
+        mla = mla.min(ceiling_left.saturating_sub(extra_debt));
+    }
+
+    let ltv = debt.saturating_mul(100).checked_div(cv).unwrap_or(0);
+
+    let available_to_withdraw_vara = if debt == 0 {
+        user_info.balance_vara
+    } else {
+        user_info
+            .balance_vara
+            .saturating_sub(user_info.balance_vara.saturating_mul(ltv) / 100)
+    };
+
+    PositionPreview {
+        cv,
+        mla,
+        ltv,
+        liquidation_threshold,
+        available_to_withdraw_vara,
+        liquidation_price: vara_liquidation_price::<VftClient>(state, user_info, debt),
+        loan_amount: user_info.loan_amount,
+        allowed: true,
+    }
+}
+
+fn current_position<VftClient: Vft<Args = GStdArgs>>(state: &VstreetState, user: &ActorId) -> Option<UserInfo> {
+    let user_info = state.users.get(user)?;
+    let mut position = user_info.clone();
+    position.loan_amount = current_loan_amount::<VftClient>(state, user_info);
+    Some(position)
+}
+
+// Public methods
+
+// Position of `user` after borrowing `amount` more of the primary VFT
+pub fn preview_take_loan<VftClient>(
+    service: &LiquidityInjectionService<VftClient>,
+    user: ActorId,
+    amount: u128,
+) -> Option<PositionPreview>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_ref();
+    let mut position = current_position::<VftClient>(state, &user)?;
+    let before = position_metrics::<VftClient>(state, &user, &position, 0);
+
+    position.loan_amount = position.loan_amount.saturating_add(amount);
+    let mut preview = position_metrics::<VftClient>(state, &user, &position, amount);
+
+    let exceeds_debt_ceiling = state
+        .cdp
+        .as_ref()
+        .map(|cdp| state.total_borrowed.saturating_add(amount) > cdp.debt_ceiling)
+        .unwrap_or(false);
+
+    preview.allowed = amount > 0
+        && amount <= state.config.max_loan_amount
+        && amount <= before.mla
+        && before.cv > 0
+        && emode::can_borrow_primary(state, &user)
+        && isolation::can_borrow_primary(state, &user)
+        && state.total_borrowed.saturating_add(amount) <= state.config.borrow_cap
+        && !exceeds_debt_ceiling;
+
+    Some(preview)
+}
+
+// Position of `user` after withdrawing `amount` TVARA of collateral
+pub fn preview_withdraw_collateral<VftClient>(
+    service: &LiquidityInjectionService<VftClient>,
+    user: ActorId,
+    amount: u128,
+) -> Option<PositionPreview>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_ref();
+    let mut position = current_position::<VftClient>(state, &user)?;
+    let before = position_metrics::<VftClient>(state, &user, &position, 0);
+
+    let amount_vara = amount.saturating_mul(state.config.one_tvara);
+    position.balance_vara = position.balance_vara.saturating_sub(amount_vara);
+    let mut preview = position_metrics::<VftClient>(state, &user, &position, 0);
+
+    preview.allowed = amount_vara > 0
+        && amount_vara <= state.config.max_collateral_withdraw
+        && amount_vara <= before.available_to_withdraw_vara;
+
+    Some(preview)
+}
+
+// Current position of `user` with interest accrued up to now
+pub fn position<VftClient>(service: &LiquidityInjectionService<VftClient>, user: ActorId) -> Option<PositionPreview>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_ref();
+    let position = current_position::<VftClient>(state, &user)?;
+
+    Some(position_metrics::<VftClient>(state, &user, &position, 0))
+}
+
+// Interest accrued on the variable loan of `user` since it was last applied
+pub fn pending_interest<VftClient>(service: &LiquidityInjectionService<VftClient>, user: ActorId) -> Option<u128>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_ref();
+    let user_info = state.users.get(&user)?;
+
+    Some(current_loan_amount::<VftClient>(state, user_info).saturating_sub(user_info.loan_amount))
+}
+
+pub fn liquidation_price<VftClient>(service: &LiquidityInjectionService<VftClient>, user: ActorId) -> Option<u128>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_ref();
+    let position = current_position::<VftClient>(state, &user)?;
+
+    Some(position_metrics::<VftClient>(state, &user, &position, 0).liquidation_price)
+}
+
+// Pool rates after depositing `amount` of liquidity, 0 gives the current rates
+pub fn preview_deposit<VftClient>(service: &LiquidityInjectionService<VftClient>, amount: u128) -> DepositPreview
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state = service.state_ref();
+    let total_deposited = state.total_deposited.saturating_add(amount);
+    let utilization_factor = LiquidityInjectionService::<VftClient>::utilization_factor_for(
+        total_deposited,
+        state.total_borrowed,
+        state.config.decimals_factor,
+    );
+    let apr = LiquidityInjectionService::<VftClient>::apr_for(&state.config, utilization_factor);
+
+    DepositPreview {
+        total_deposited,
+        utilization_factor,
+        apr,
+        interest_rate: apr.saturating_add(state.config.dev_fee),
+        allowed: state.cdp.is_none()
+            && amount > 0
+            && amount <= state.config.max_liquidity_deposit
+            && total_deposited <= state.config.supply_cap,
+    }
+}
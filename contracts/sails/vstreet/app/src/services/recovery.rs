@@ -0,0 +1,445 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{exec, msg, calls::GStdArgs},
+};
+use sails_rs::calls::ActionIo;
+use gstd::critical;
+
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::clients::extended_vft_client::vft::io::TransferFrom;
+use crate::services::vst_liquidity_injection::{LiquidityInjectionService, market_state};
+use crate::states::vstreet_state::{
+    VstreetState,
+    UserInfo,
+    Reserve,
+    ReservePosition,
+    PendingOperation,
+    PendingStatus,
+    PositionSnapshot
+};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_PENDING_OPERATION_NOT_FOUND,
+    ERROR_SIGNAL_GAS_NOT_RESERVED
+};
+
+// Recovery for operations that await external calls. A message that runs out of
+// gas or traps after an await never reaches its rollback branch, and everything it
+// did before the await is already committed. Each such operation is tracked in
+// `pending_operations`:
+// - gas is set aside for `handle_signal` when it starts, and a critical hook
+//   restores the positions from a snapshot if the message dies before it finishes
+// - every call to another program (VFT transfers, mints, burns, approvals and
+//   swaps) carries a reply deposit, so its reply hook can record the outcome
+//   even when the waiting message no longer has gas
+// - calls give up after `REPLY_TIMEOUT_BLOCKS`, which takes the regular
+//   rollback branch instead of waiting forever
+// - the snapshot is only restored when every call sent was seen to fail, a
+//   call that went through or never replied leaves the operation for review
+
+// Gas kept for `handle_signal` so the critical hook can always run
+pub const SIGNAL_GAS_RESERVE: u64 = 5_000_000_000;
+// Gas reserved with each call for its reply hook
+pub const REPLY_DEPOSIT: u64 = 10_000_000_000;
+// Blocks to wait for a reply before taking the rollback branch
+pub const REPLY_TIMEOUT_BLOCKS: u32 = 30;
+
+// Internal helpers
+
+// Everything `user` holds in the maps a recoverable operation writes
+fn snapshot_position(state: &VstreetState, user: &ActorId) -> PositionSnapshot {
+    PositionSnapshot {
+        user_info: state.users.get(user).cloned(),
+        reserve_positions: state
+            .reserves
+            .iter()
+            .filter_map(|(asset, reserve)| reserve.positions.get(user).map(|position| (*asset, position.clone())))
+            .collect(),
+        vara_pool_position: state.vara_pool.positions.get(user).cloned(),
+        term_loans: state
+            .term_loans
+            .iter()
+            .filter(|(_, term_loan)| term_loan.borrower == *user)
+            .map(|(loan_id, term_loan)| (*loan_id, term_loan.clone()))
+            .collect(),
+        delegations: state
+            .delegations
+            .iter()
+            .filter(|((delegator, delegatee), _)| delegator == user || delegatee == user)
+            .map(|(key, amount)| (*key, *amount))
+            .collect(),
+        savings_balance: state.savings.balances.get(user).copied(),
+    }
+}
+
+// Put a reserve position back, keeping the reserve totals in step
+fn restore_reserve_position(reserve: &mut Reserve, user: &ActorId, snapshot: Option<ReservePosition>) {
+    let current = reserve.positions.get(user).cloned().unwrap_or_default();
+    let before = snapshot.clone().unwrap_or_default();
+
+    reserve.total_deposited = reserve
+        .total_deposited
+        .saturating_sub(current.balance)
+        .saturating_add(before.balance);
+    reserve.total_borrowed = reserve
+        .total_borrowed
+        .saturating_sub(current.loan_amount)
+        .saturating_add(before.loan_amount);
+
+    match snapshot {
+        Some(position) => {
+            reserve.positions.insert(*user, position);
+        }
+        None => {
+            reserve.positions.remove(user);
+        }
+    }
+}
+
+// Undo what the operation did to a position, keeping the pool totals in step
+fn restore_position(state: &mut VstreetState, user: &ActorId, snapshot: PositionSnapshot) {
+    let current = state.users.get(user).cloned();
+    let before = snapshot.user_info;
+    let field = |user_info: &Option<UserInfo>, get: fn(&UserInfo) -> u128| user_info.as_ref().map(get).unwrap_or(0);
+
+    state.total_deposited = state
+        .total_deposited
+        .saturating_sub(field(&current, |u| u.balance))
+        .saturating_add(field(&before, |u| u.balance));
+    state.total_borrowed = state
+        .total_borrowed
+        .saturating_sub(field(&current, |u| u.loan_amount))
+        .saturating_add(field(&before, |u| u.loan_amount));
+    state.total_collateral_vara = state
+        .total_collateral_vara
+        .saturating_sub(field(&current, |u| u.balance_vara))
+        .saturating_add(field(&before, |u| u.balance_vara));
+    state.total_stable_collateral = state
+        .total_stable_collateral
+        .saturating_sub(field(&current, |u| u.stable_collateral))
+        .saturating_add(field(&before, |u| u.stable_collateral));
+
+    for (asset, collateral_asset) in state.collateral_assets.iter_mut() {
+        let balance = |user_info: &Option<UserInfo>| {
+            user_info
+                .as_ref()
+                .and_then(|u| u.collateral_balances.get(asset).copied())
+                .unwrap_or(0)
+        };
+        collateral_asset.total_deposited = collateral_asset
+            .total_deposited
+            .saturating_sub(balance(&current))
+            .saturating_add(balance(&before));
+    }
+
+    match before {
+        Some(user_info) => {
+            state.users.insert(*user, user_info);
+        }
+        None => {
+            state.users.remove(user);
+        }
+    }
+
+    for (asset, reserve) in state.reserves.iter_mut() {
+        let position = snapshot.reserve_positions.get(asset).cloned();
+        restore_reserve_position(reserve, user, position);
+    }
+    restore_reserve_position(&mut state.vara_pool, user, snapshot.vara_pool_position);
+
+    // Term loan principal is part of the primary pool debt
+    let opened: Vec<u64> = state
+        .term_loans
+        .iter()
+        .filter(|(_, term_loan)| term_loan.borrower == *user)
+        .map(|(loan_id, _)| *loan_id)
+        .collect();
+    for loan_id in opened {
+        if let Some(term_loan) = state.term_loans.remove(&loan_id) {
+            state.total_borrowed = state.total_borrowed.saturating_sub(term_loan.principal);
+        }
+    }
+    for (loan_id, term_loan) in snapshot.term_loans {
+        state.total_borrowed = state.total_borrowed.saturating_add(term_loan.principal);
+        state.term_loans.insert(loan_id, term_loan);
+    }
+
+    state
+        .delegations
+        .retain(|(delegator, delegatee), _| delegator != user && delegatee != user);
+    state.delegations.extend(snapshot.delegations);
+
+    let savings = &mut state.savings;
+    savings.total_normalized = savings
+        .total_normalized
+        .saturating_sub(savings.balances.get(user).copied().unwrap_or(0))
+        .saturating_add(snapshot.savings_balance.unwrap_or(0));
+    match snapshot.savings_balance {
+        Some(balance) => {
+            savings.balances.insert(*user, balance);
+        }
+        None => {
+            savings.balances.remove(user);
+        }
+    }
+}
+
+// Critical hook, runs in `handle_signal` when the message that started the operation died
+fn recover(market_id: Option<u32>, operation_id: MessageId) {
+    let state = market_state(market_id);
+
+    let Some(operation) = state.pending_operations.get(&operation_id).cloned() else {
+        return;
+    };
+
+    if operation.status != PendingStatus::InFlight {
+        return;
+    }
+
+    // A call that went through, or one whose reply never came back, may have
+    // moved tokens the snapshot knows nothing about
+    let all_failed = operation.confirmed_transfers == 0
+        && operation.failed_transfers == operation.sent_transfers;
+
+    let status = if all_failed {
+        for (user, snapshot) in operation.snapshot {
+            restore_position(state, &user, snapshot);
+        }
+        PendingStatus::RolledBack
+    } else {
+        PendingStatus::NeedsReview
+    };
+
+    for user in operation.users.iter() {
+        state.locks.users.remove(user);
+    }
+
+    if let Some(operation) = state.pending_operations.get_mut(&operation_id) {
+        operation.status = status;
+    }
+}
+
+// Critical hook of a batch job, runs in `handle_signal` when its message died
+fn release_global_lock(market_id: Option<u32>) {
+    market_state(market_id).locks.global = None;
+}
+
+// Reply hook, runs in `handle_reply` with the reply deposit of the call
+fn record_reply(
+    market_id: Option<u32>,
+    operation_id: MessageId,
+    parties: Vec<ActorId>,
+    executed: fn(&[u8]) -> bool,
+) {
+    let state = market_state(market_id);
+    let confirmed = msg::load_bytes()
+        .map(|payload| executed(&payload))
+        .unwrap_or(false);
+
+    match state.pending_operations.get_mut(&operation_id) {
+        Some(operation) => {
+            if confirmed {
+                operation.confirmed_transfers = operation.confirmed_transfers.saturating_add(1);
+            } else {
+                operation.failed_transfers = operation.failed_transfers.saturating_add(1);
+            }
+        }
+        // The operation already gave up on this reply and rolled back,
+        // so tokens that moved anyway need an operator
+        None if confirmed => {
+            state.pending_operations.insert(
+                operation_id,
+                PendingOperation {
+                    users: parties,
+                    started_at: exec::block_height(),
+                    status: PendingStatus::NeedsReview,
+                    sent_transfers: 1,
+                    confirmed_transfers: 1,
+                    failed_transfers: 0,
+                    snapshot: Vec::new(),
+                },
+            );
+        }
+        None => {}
+    }
+}
+
+// VFT calls report whether they went through
+pub(crate) fn returned_true<A: ActionIo<Reply = bool>>(payload: &[u8]) -> bool {
+    A::decode_reply(payload).unwrap_or(false)
+}
+
+// Approvals move no tokens, a rollback stays safe whatever they replied
+pub(crate) fn moved_nothing(_payload: &[u8]) -> bool {
+    false
+}
+
+// Swaps either reply with the amount out or fail
+pub(crate) fn replied<A: ActionIo>(payload: &[u8]) -> bool {
+    A::decode_reply(payload).is_ok()
+}
+
+// Without gas for `handle_signal` the critical hook never runs, so an operation
+// that could not be recovered is not started at all
+fn reserve_signal_gas<VftClient>(service: &mut LiquidityInjectionService<VftClient>) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if exec::system_reserve_gas(SIGNAL_GAS_RESERVE).is_err() {
+        let error_message = ERROR_SIGNAL_GAS_NOT_RESERVED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    Ok(())
+}
+
+// Track the running message as an operation on `users`
+pub(crate) fn begin<VftClient>(service: &mut LiquidityInjectionService<VftClient>, users: &[ActorId]) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    reserve_signal_gas(service)?;
+
+    let operation_id = msg::id();
+    let market_id = service.market_id();
+    let state_mut = service.state_mut();
+
+    let snapshot = users
+        .iter()
+        .map(|user| (*user, snapshot_position(state_mut, user)))
+        .collect();
+
+    state_mut.pending_operations.insert(
+        operation_id,
+        PendingOperation {
+            users: users.to_vec(),
+            started_at: exec::block_height(),
+            status: PendingStatus::InFlight,
+            sent_transfers: 0,
+            confirmed_transfers: 0,
+            failed_transfers: 0,
+            snapshot,
+        },
+    );
+
+    critical::set_hook(move || recover(market_id, operation_id));
+
+    Ok(())
+}
+
+pub(crate) fn finish<VftClient>(service: &mut LiquidityInjectionService<VftClient>)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let operation_id = msg::id();
+    let state_mut = service.state_mut();
+
+    let in_flight = state_mut
+        .pending_operations
+        .get(&operation_id)
+        .map(|operation| operation.status == PendingStatus::InFlight)
+        .unwrap_or(false);
+
+    if in_flight {
+        state_mut.pending_operations.remove(&operation_id);
+    }
+
+    let _ = critical::take_hook();
+}
+
+// Release the global lock from `handle_signal` if the batch job dies after an await
+pub(crate) fn begin_global<VftClient>(service: &mut LiquidityInjectionService<VftClient>) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    reserve_signal_gas(service)?;
+
+    let market_id = service.market_id();
+    critical::set_hook(move || release_global_lock(market_id));
+
+    Ok(())
+}
+
+pub(crate) fn finish_global() {
+    let _ = critical::take_hook();
+}
+
+// Arguments for a call to another program on behalf of `parties`. Only calls of a
+// tracked operation get a reply hook, `executed` tells from the reply whether the
+// call went through.
+pub(crate) fn call_args<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    parties: Vec<ActorId>,
+    executed: fn(&[u8]) -> bool,
+) -> GStdArgs
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let operation_id = msg::id();
+    let market_id = service.market_id();
+    let args = GStdArgs::default().with_wait_up_to(Some(REPLY_TIMEOUT_BLOCKS));
+
+    let Some(operation) = service.state_mut().pending_operations.get_mut(&operation_id) else {
+        return args;
+    };
+    operation.sent_transfers = operation.sent_transfers.saturating_add(1);
+
+    args.with_reply_deposit(Some(REPLY_DEPOSIT))
+        .with_reply_hook(move || record_reply(market_id, operation_id, parties, executed))
+}
+
+// Arguments for a VFT transfer
+pub(crate) fn transfer_args<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    from: ActorId,
+    to: ActorId,
+) -> GStdArgs
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    call_args(service, vec![from, to], returned_true::<TransferFrom>)
+}
+
+// Public methods
+
+pub fn pending_operations(state: &VstreetState) -> Vec<(MessageId, PendingOperation)> {
+    state
+        .pending_operations
+        .iter()
+        .map(|(operation_id, operation)| (*operation_id, operation.clone()))
+        .collect()
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// Drop a recovered operation once an operator has settled it
+pub fn clear_pending_operation<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    operation_id: MessageId,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    // Operations in flight belong to a live message, which still holds their locks
+    let clearable = state_mut
+        .pending_operations
+        .get(&operation_id)
+        .map(|operation| operation.status != PendingStatus::InFlight)
+        .unwrap_or(false);
+
+    if !clearable {
+        let error_message = ERROR_PENDING_OPERATION_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    state_mut.pending_operations.remove(&operation_id);
+
+    service.notify_pending_operation_cleared(operation_id);
+
+    Ok(())
+}
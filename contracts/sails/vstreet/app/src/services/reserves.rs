@@ -0,0 +1,588 @@
+use sails_rs::{
+    prelude::*,
+    collections::BTreeMap,
+    gstd::{
+        msg,
+        debug,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::{term_loans, emode, isolation};
+use crate::states::vstreet_state::{VstreetState, Reserve, ReserveParams, Config};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT,
+    ERROR_USER_NOT_FOUND,
+    ERROR_RESERVE_NOT_FOUND,
+    ERROR_RESERVE_ALREADY_EXISTS,
+    ERROR_RESERVE_DISABLED,
+    ERROR_RESERVE_INSUFFICIENT_LIQUIDITY,
+    ERROR_SUPPLY_CAP_EXCEEDED,
+    ERROR_BORROW_CAP_EXCEEDED,
+    ERROR_EMODE_ASSET_NOT_ALLOWED,
+    ERROR_ISOLATION_ASSET_NOT_ALLOWED
+};
+
+// Internal helpers
+
+// Outstanding debt across all reserves, expressed in primary token units.
+// Reserves host stable VFTs, so debt is valued 1:1 after decimals normalization.
+pub fn reserve_debt_value(state: &VstreetState, user: &ActorId) -> u128 {
+    state
+        .reserves
+        .values()
+        .filter_map(|reserve| {
+            reserve.positions.get(user).map(|position| {
+                normalize_amount(position.loan_amount, reserve, &state.config)
+            })
+        })
+        .fold(0, |total, debt| total.saturating_add(debt))
+}
+
+// Convert raw reserve token units into primary token units
+pub fn normalize_amount(amount: u128, reserve: &Reserve, config: &Config) -> u128 {
+    amount
+        .saturating_mul(config.decimals_factor)
+        .checked_div(reserve.decimals_factor)
+        .unwrap_or(0)
+}
+
+// Same rate model as the primary pool, with the reserve's own parameters
+pub fn refresh_reserve_rates(reserve: &mut Reserve, config: &Config) {
+    reserve.utilization_factor = if reserve.total_deposited == 0 || reserve.total_borrowed == 0 {
+        0
+    } else {
+        reserve
+            .total_borrowed
+            .saturating_mul(config.decimals_factor)
+            .saturating_mul(100)
+            .checked_div(reserve.total_deposited)
+            .unwrap_or(0)
+    };
+
+    let variable_rate = reserve
+        .utilization_factor
+        .saturating_mul(reserve.risk_multiplier)
+        .checked_div(config.decimals_factor)
+        .unwrap_or(0);
+
+    reserve.apr = reserve.base_rate.saturating_add(variable_rate);
+    reserve.interest_rate = reserve.apr.saturating_add(reserve.dev_fee);
+}
+
+// Accrue borrower interest since the last update and hand it to suppliers
+// pro-rata to their balance. The dev fee share (and rounding dust) is kept
+// in `accrued_fees`.
+pub fn accrue_reserve(reserve: &mut Reserve, config: &Config, current_timestamp: u128) {
+    let time_elapsed = current_timestamp.saturating_sub(reserve.last_updated) / 1000;
+
+    if time_elapsed == 0 {
+        return;
+    }
+
+    reserve.last_updated = current_timestamp;
+
+    if reserve.total_borrowed == 0 || reserve.interest_rate == 0 {
+        return;
+    }
+
+    let denominator = config
+        .year_in_seconds
+        .saturating_mul(config.decimals_factor)
+        .saturating_mul(100);
+
+    let mut total_interest: u128 = 0;
+
+    for position in reserve.positions.values_mut() {
+        if position.loan_amount > 0 {
+            let interest = position
+                .loan_amount
+                .saturating_mul(reserve.interest_rate)
+                .saturating_mul(time_elapsed)
+                .checked_div(denominator)
+                .unwrap_or(0);
+
+            position.loan_amount = position.loan_amount.saturating_add(interest);
+            total_interest = total_interest.saturating_add(interest);
+        }
+    }
+
+    if total_interest == 0 {
+        return;
+    }
+
+    let fees = total_interest
+        .saturating_mul(reserve.dev_fee)
+        .checked_div(reserve.interest_rate)
+        .unwrap_or(0);
+    let suppliers_interest = total_interest.saturating_sub(fees);
+
+    let total_deposited = reserve.total_deposited;
+    let mut distributed: u128 = 0;
+
+    if total_deposited > 0 {
+        for position in reserve.positions.values_mut() {
+            if position.balance > 0 {
+                let share = suppliers_interest
+                    .saturating_mul(position.balance)
+                    .checked_div(total_deposited)
+                    .unwrap_or(0);
+
+                position.balance = position.balance.saturating_add(share);
+                distributed = distributed.saturating_add(share);
+            }
+        }
+    }
+
+    debug!("Reserve interest accrued: {}, distributed: {}", total_interest, distributed);
+
+    reserve.total_borrowed = reserve.total_borrowed.saturating_add(total_interest);
+    reserve.total_deposited = reserve.total_deposited.saturating_add(distributed);
+    reserve.accrued_fees = reserve
+        .accrued_fees
+        .saturating_add(total_interest.saturating_sub(distributed));
+}
+
+// Accrue and refresh every reserve, so cross-reserve debt checks use current values
+pub fn accrue_all_reserves(state: &mut VstreetState) {
+    let current_timestamp = exec::block_timestamp() as u128;
+
+    for reserve in state.reserves.values_mut() {
+        accrue_reserve(reserve, &state.config, current_timestamp);
+        refresh_reserve_rates(reserve, &state.config);
+    }
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+// List a new stable reserve
+pub fn add_reserve<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    decimals_factor: u128,
+    params: ReserveParams,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    if state_mut.reserves.contains_key(&asset) || state_mut.vft_contract_id == Some(asset) {
+        let error_message = ERROR_RESERVE_ALREADY_EXISTS.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if decimals_factor == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let mut reserve = Reserve {
+        decimals_factor,
+        total_deposited: 0,
+        total_borrowed: 0,
+        accrued_fees: 0,
+        utilization_factor: 0,
+        apr: 0,
+        interest_rate: 0,
+        base_rate: params.base_rate,
+        risk_multiplier: params.risk_multiplier,
+        dev_fee: params.dev_fee,
+        supply_cap: params.supply_cap,
+        borrow_cap: params.borrow_cap,
+        enabled: true,
+        last_updated: exec::block_timestamp() as u128,
+        positions: BTreeMap::new(),
+    };
+    refresh_reserve_rates(&mut reserve, &state_mut.config);
+
+    state_mut.reserves.insert(asset, reserve);
+
+    service.notify_reserve_updated(asset);
+
+    Ok(())
+}
+
+// Change the rate model or caps of a reserve. Disabling it only blocks new
+// deposits and loans, withdrawals and repayments keep working.
+pub fn update_reserve<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    params: ReserveParams,
+    enabled: bool,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let current_timestamp = exec::block_timestamp() as u128;
+
+    let reserve = match state_mut.reserves.get_mut(&asset) {
+        Some(reserve) => reserve,
+        None => {
+            let error_message = ERROR_RESERVE_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    // Settle interest at the old rates before switching the model
+    accrue_reserve(reserve, &state_mut.config, current_timestamp);
+
+    reserve.base_rate = params.base_rate;
+    reserve.risk_multiplier = params.risk_multiplier;
+    reserve.dev_fee = params.dev_fee;
+    reserve.supply_cap = params.supply_cap;
+    reserve.borrow_cap = params.borrow_cap;
+    reserve.enabled = enabled;
+
+    refresh_reserve_rates(reserve, &state_mut.config);
+
+    service.notify_reserve_updated(asset);
+
+    Ok(())
+}
+
+// Public methods
+
+// Deposit liquidity into a reserve
+pub async fn deposit_reserve_liquidity<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let caller = msg::source();
+
+    accrue_all_reserves(state_mut);
+
+    let reserve = match state_mut.reserves.get_mut(&asset) {
+        Some(reserve) => reserve,
+        None => {
+            let error_message = ERROR_RESERVE_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if !reserve.enabled {
+        let error_message = ERROR_RESERVE_DISABLED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if reserve.total_deposited.saturating_add(amount) > reserve.supply_cap {
+        let error_message = ERROR_SUPPLY_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Transfer tokens from user to contract
+    let result = service.transfer_asset_tokens(asset, caller, exec::program_id(), amount).await;
+
+    if result.is_err() {
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+    let position = reserve.positions.entry(caller).or_default();
+
+    position.balance = position.balance.saturating_add(amount);
+    reserve.total_deposited = reserve.total_deposited.saturating_add(amount);
+
+    refresh_reserve_rates(reserve, &state_mut.config);
+
+    service.notify_reserve_deposit(asset, amount);
+
+    Ok(())
+}
+
+// Withdraw liquidity (principal plus credited interest) from a reserve
+pub async fn withdraw_reserve_liquidity<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let caller = msg::source();
+
+    accrue_all_reserves(state_mut);
+
+    let reserve = match state_mut.reserves.get_mut(&asset) {
+        Some(reserve) => reserve,
+        None => {
+            let error_message = ERROR_RESERVE_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    let balance = reserve.positions.get(&caller).map(|position| position.balance).unwrap_or(0);
+
+    if amount == 0 || amount > balance {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Only idle liquidity can leave the reserve
+    if amount > reserve.total_deposited.saturating_sub(reserve.total_borrowed) {
+        let error_message = ERROR_RESERVE_INSUFFICIENT_LIQUIDITY.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: update state BEFORE the external call to prevent re-entrancy.
+    let position = reserve.positions.get_mut(&caller).unwrap();
+    position.balance = position.balance.saturating_sub(amount);
+    reserve.total_deposited = reserve.total_deposited.saturating_sub(amount);
+
+    // Transfer tokens from contract to user
+    let result = service.transfer_asset_tokens(asset, exec::program_id(), caller, amount).await;
+
+    // If transfer fails roll back the state changes to keep accounting consistent
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+        let position = reserve.positions.entry(caller).or_default();
+        position.balance = position.balance.saturating_add(amount);
+        reserve.total_deposited = reserve.total_deposited.saturating_add(amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+    refresh_reserve_rates(reserve, &state_mut.config);
+
+    service.notify_reserve_withdraw(asset, amount);
+
+    Ok(())
+}
+
+// Borrow from a reserve against the caller's collateral.
+// The borrowing power (MLA) is shared with the primary pool and all other reserves.
+pub async fn take_reserve_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Refresh accrued interest everywhere BEFORE reading MLA
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+    accrue_all_reserves(service.state_mut());
+
+    let state_mut = service.state_mut();
+
+    if !state_mut.users.contains_key(&caller) {
+        let error_message = ERROR_USER_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !emode::can_borrow(state_mut, &caller, &asset) {
+        let error_message = ERROR_EMODE_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !isolation::can_borrow(state_mut, &caller, &asset) {
+        let error_message = ERROR_ISOLATION_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let (enabled, normalized_amount, over_cap, available) = match state_mut.reserves.get(&asset) {
+        Some(reserve) => (
+            reserve.enabled,
+            normalize_amount(amount, reserve, &state_mut.config),
+            reserve.total_borrowed.saturating_add(amount) > reserve.borrow_cap,
+            reserve.total_deposited.saturating_sub(reserve.total_borrowed),
+        ),
+        None => {
+            let error_message = ERROR_RESERVE_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if !enabled {
+        let error_message = ERROR_RESERVE_DISABLED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if over_cap {
+        let error_message = ERROR_BORROW_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount > available {
+        let error_message = ERROR_RESERVE_INSUFFICIENT_LIQUIDITY.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let _ = service.calculate_mla(caller);
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+
+    // Ensure user has collateral before allowing loan
+    if user_info.cv == 0 {
+        let error_message = "Insufficient collateral: cv is 0".to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 || normalized_amount > user_info.mla {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let was_loan_active = user_info.is_loan_active;
+
+    // CEI: update all loan state BEFORE the external transfer
+    user_info.is_loan_active = true;
+
+    let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+    let position = reserve.positions.entry(caller).or_default();
+    position.loan_amount = position.loan_amount.saturating_add(amount);
+    reserve.total_borrowed = reserve.total_borrowed.saturating_add(amount);
+    refresh_reserve_rates(reserve, &state_mut.config);
+
+    service.update_user_ltv(caller);
+    let _ = service.calculate_mla(caller);
+    LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+
+    // Transfer tokens from contract to user AFTER state has been updated (CEI).
+    let result = service.transfer_asset_tokens(asset, exec::program_id(), caller, amount).await;
+
+    // Roll back state if the transfer fails
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+        if let Some(position) = reserve.positions.get_mut(&caller) {
+            position.loan_amount = position.loan_amount.saturating_sub(amount);
+        }
+        reserve.total_borrowed = reserve.total_borrowed.saturating_sub(amount);
+        refresh_reserve_rates(reserve, &state_mut.config);
+
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        user_info.is_loan_active = was_loan_active;
+        service.update_user_ltv(caller);
+        let _ = service.calculate_mla(caller);
+        LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.notify_reserve_loan_taken(asset, amount);
+
+    Ok(())
+}
+
+// Repay part or all of a reserve loan
+pub async fn pay_reserve_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    asset: ActorId,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+    let state_mut = service.state_mut();
+
+    accrue_all_reserves(state_mut);
+
+    let reserve = match state_mut.reserves.get_mut(&asset) {
+        Some(reserve) => reserve,
+        None => {
+            let error_message = ERROR_RESERVE_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    let loan_amount = reserve.positions.get(&caller).map(|position| position.loan_amount).unwrap_or(0);
+
+    if amount == 0 || amount > loan_amount {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: update state BEFORE the external transfer to prevent re-entrancy.
+    let position = reserve.positions.get_mut(&caller).unwrap();
+    position.loan_amount = position.loan_amount.saturating_sub(amount);
+    reserve.total_borrowed = reserve.total_borrowed.saturating_sub(amount);
+
+    let still_in_debt = term_loans::secondary_debt_value(state_mut, &caller) > 0;
+    if let Some(user_info) = state_mut.users.get_mut(&caller) {
+        user_info.is_loan_active = user_info.loan_amount > 0 || still_in_debt;
+    }
+
+    // Transfer tokens from user to contract AFTER state update (CEI).
+    let result = service.transfer_asset_tokens(asset, caller, exec::program_id(), amount).await;
+
+    // If transfer fails, roll back state
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+        if let Some(position) = reserve.positions.get_mut(&caller) {
+            position.loan_amount = position.loan_amount.saturating_add(amount);
+        }
+        reserve.total_borrowed = reserve.total_borrowed.saturating_add(amount);
+        if let Some(user_info) = state_mut.users.get_mut(&caller) {
+            user_info.is_loan_active = true;
+        }
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    let reserve = state_mut.reserves.get_mut(&asset).unwrap();
+    refresh_reserve_rates(reserve, &state_mut.config);
+
+    service.update_user_ltv(caller);
+    let _ = service.calculate_mla(caller);
+    if let Some(user_info) = state_mut.users.get_mut(&caller) {
+        LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+    }
+
+    service.notify_reserve_loan_payed(asset, amount);
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use sails_rs::{
+    prelude::*,
+    collections::btree_map::Entry,
+    gstd::exec,
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::states::vstreet_state::VstreetState;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_INVALID_WARNING_BAND
+};
+
+// Internal helpers
+
+fn is_at_risk(state: &VstreetState, user: &ActorId) -> bool {
+    match state.users.get(user) {
+        Some(user_info) => {
+            user_info.ltv > 0
+                && user_info.ltv.saturating_add(state.risk_watch.warning_band) >= user_info.liquidation_threshold
+        }
+        None => false,
+    }
+}
+
+// Flag or clear a position once its LTV was updated. `PositionAtRisk` is only
+// emitted when the position enters the warning band, not on every update inside it.
+pub(crate) fn track<VftClient>(service: &mut LiquidityInjectionService<VftClient>, user: ActorId)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    if !is_at_risk(state_mut, &user) {
+        state_mut.risk_watch.at_risk.remove(&user);
+        return;
+    }
+
+    if let Entry::Vacant(entry) = state_mut.risk_watch.at_risk.entry(user) {
+        entry.insert(exec::block_height());
+        let user_info = state_mut.users.get(&user).unwrap();
+        service.notify_position_at_risk(user, user_info.ltv, user_info.liquidation_threshold);
+    }
+}
+
+// Flagged positions after `cursor` (exclusive) as (user, ltv, liquidation threshold)
+pub fn positions_at_risk(state: &VstreetState, limit: u32, cursor: Option<ActorId>) -> Vec<(ActorId, u128, u128)> {
+    state
+        .risk_watch
+        .at_risk
+        .keys()
+        .filter(|user| cursor.map(|cursor| **user > cursor).unwrap_or(true))
+        .filter_map(|user| {
+            state
+                .users
+                .get(user)
+                .map(|user_info| (*user, user_info.ltv, user_info.liquidation_threshold))
+        })
+        .take(limit as usize)
+        .collect()
+}
+
+// Risk methods
+// Callers are expected to have checked risk privileges already.
+
+pub fn set_risk_warning_band<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    warning_band: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    if warning_band > 100 {
+        let error_message = ERROR_INVALID_WARNING_BAND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    state_mut.risk_watch.warning_band = warning_band;
+
+    // Re-evaluate every position against the new band
+    for user in state_mut.users.keys().cloned().collect::<Vec<_>>() {
+        track(service, user);
+    }
+
+    service.notify_risk_warning_band_updated(warning_band);
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::cdp;
+use crate::states::vstreet_state::VstreetState;
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT
+};
+
+// Precision of the savings rate accumulator
+pub const CHI_PRECISION: u128 = 1_000_000_000_000;
+
+// Internal helpers
+
+// Savings yield owed since the last update and the accumulator it leads to.
+// Yield is funded from accrued stability fees first, then from the rewards pool,
+// and never exceeds what those can cover. Returns (chi, from_fees, from_pool).
+fn accrue(state: &VstreetState, current_timestamp: u128) -> (u128, u128, u128) {
+    let savings = &state.savings;
+    let time_elapsed = current_timestamp.saturating_sub(savings.last_updated) / 1000;
+
+    if time_elapsed == 0 || savings.rate == 0 || savings.total_normalized == 0 {
+        return (savings.chi, 0, 0);
+    }
+
+    let total_balance = savings
+        .total_normalized
+        .saturating_mul(savings.chi)
+        / CHI_PRECISION;
+
+    let denominator = state
+        .config
+        .year_in_seconds
+        .saturating_mul(state.config.decimals_factor)
+        .saturating_mul(100);
+
+    let owed = total_balance
+        .saturating_mul(savings.rate)
+        .saturating_mul(time_elapsed)
+        .checked_div(denominator)
+        .unwrap_or(0);
+
+    let accrued_fees = state.cdp.as_ref().map(|cdp| cdp.accrued_fees).unwrap_or(0);
+    let from_fees = owed.min(accrued_fees);
+    let from_pool = owed.saturating_sub(from_fees).min(state.available_rewards_pool);
+
+    let chi = savings.chi.saturating_add(
+        from_fees
+            .saturating_add(from_pool)
+            .saturating_mul(CHI_PRECISION)
+            / savings.total_normalized,
+    );
+
+    (chi, from_fees, from_pool)
+}
+
+// Move the accumulator forward to `current_timestamp` and charge its funding
+pub fn drip(state: &mut VstreetState, current_timestamp: u128) {
+    let (chi, from_fees, from_pool) = accrue(state, current_timestamp);
+
+    if let Some(cdp) = state.cdp.as_mut() {
+        cdp.accrued_fees = cdp.accrued_fees.saturating_sub(from_fees);
+    }
+    state.available_rewards_pool = state.available_rewards_pool.saturating_sub(from_pool);
+    state.savings.chi = chi;
+    state.savings.last_updated = current_timestamp;
+}
+
+// Savings balance of a user, including yield not yet dripped
+pub fn balance_of(state: &VstreetState, user: &ActorId) -> u128 {
+    let (chi, _, _) = accrue(state, exec::block_timestamp() as u128);
+
+    state
+        .savings
+        .balances
+        .get(user)
+        .copied()
+        .unwrap_or(0)
+        .saturating_mul(chi)
+        / CHI_PRECISION
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+pub fn set_savings_rate<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    rate: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
+    // Settle yield at the old rate first
+    drip(state_mut, exec::block_timestamp() as u128);
+    state_mut.savings.rate = rate;
+
+    service.notify_savings_rate_updated(rate);
+
+    Ok(())
+}
+
+// Public methods
+
+// Lock primary stable into savings
+pub async fn deposit_savings<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+    let state_mut = service.state_mut();
+
+    if amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    drip(state_mut, exec::block_timestamp() as u128);
+
+    // Rounded down so a deposit never credits more than it brings
+    let normalized = amount.saturating_mul(CHI_PRECISION) / state_mut.savings.chi;
+
+    if normalized == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Pull (or burn, in CDP mode) the stable before crediting it
+    if cdp::collect(service, caller, amount).await.is_err() {
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    let balance = state_mut.savings.balances.entry(caller).or_insert(0);
+    *balance = balance.saturating_add(normalized);
+    state_mut.savings.total_normalized = state_mut.savings.total_normalized.saturating_add(normalized);
+
+    service.notify_savings_deposit(amount);
+
+    Ok(())
+}
+
+// Withdraw primary stable and accumulated yield from savings
+pub async fn withdraw_savings<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+    let state_mut = service.state_mut();
+
+    drip(state_mut, exec::block_timestamp() as u128);
+
+    let chi = state_mut.savings.chi;
+    let user_normalized = state_mut.savings.balances.get(&caller).copied().unwrap_or(0);
+
+    // Rounded up so a withdrawal never takes more than it burns
+    let normalized = amount
+        .saturating_mul(CHI_PRECISION)
+        .saturating_add(chi - 1)
+        / chi;
+
+    if amount == 0 || normalized > user_normalized {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: debit the savings balance BEFORE the external transfer
+    let remaining = user_normalized - normalized;
+    if remaining == 0 {
+        state_mut.savings.balances.remove(&caller);
+    } else {
+        state_mut.savings.balances.insert(caller, remaining);
+    }
+    state_mut.savings.total_normalized = state_mut.savings.total_normalized.saturating_sub(normalized);
+
+    // Transfer (or mint, in CDP mode) the stable back to the user
+    if cdp::disburse(service, caller, amount).await.is_err() {
+        let state_mut = service.state_mut();
+        state_mut.savings.balances.insert(caller, user_normalized);
+        state_mut.savings.total_normalized = state_mut.savings.total_normalized.saturating_add(normalized);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.notify_savings_withdraw(amount);
+
+    Ok(())
+}
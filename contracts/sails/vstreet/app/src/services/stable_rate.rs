@@ -0,0 +1,254 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::LiquidityInjectionService;
+use crate::services::term_loans::refresh_user_position;
+use crate::services::{emode, isolation};
+use crate::states::vstreet_state::{UserInfo, Config};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT,
+    ERROR_USER_NOT_FOUND,
+    ERROR_BORROW_CAP_EXCEEDED,
+    ERROR_STABLE_RATE_NOT_DIVERGED,
+    ERROR_EMODE_ASSET_NOT_ALLOWED,
+    ERROR_ISOLATION_ASSET_NOT_ALLOWED
+};
+
+// Internal helpers
+
+// Apply interest on the stable-rate debt at the user's locked rate
+pub fn accrue_stable_debt(user_info: &mut UserInfo, config: &Config, current_timestamp: u128) {
+    let time_elapsed = current_timestamp.saturating_sub(user_info.stable_borrow_last_updated) / 1000;
+
+    if time_elapsed == 0 {
+        return;
+    }
+
+    let denominator = config
+        .year_in_seconds
+        .saturating_mul(config.decimals_factor)
+        .saturating_mul(100);
+
+    let interest = user_info
+        .stable_loan_amount
+        .saturating_mul(user_info.stable_rate)
+        .saturating_mul(time_elapsed)
+        .checked_div(denominator)
+        .unwrap_or(0);
+
+    user_info.stable_loan_amount = user_info.stable_loan_amount.saturating_add(interest);
+    user_info.stable_borrow_last_updated = current_timestamp;
+}
+
+// Public methods
+
+// Borrow at a stable rate: the current variable rate plus the stable premium.
+// New debt blends into the existing stable debt at a weighted average rate.
+pub async fn take_stable_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Refresh accrued interest BEFORE reading MLA
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    {
+        let state_mut = service.state_mut();
+        if !state_mut.users.contains_key(&caller) {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    }
+
+    let _ = service.calculate_mla(caller);
+    let state_mut = service.state_mut();
+
+    if !emode::can_borrow_primary(state_mut, &caller) {
+        let error_message = ERROR_EMODE_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !isolation::can_borrow_primary(state_mut, &caller) {
+        let error_message = ERROR_ISOLATION_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+
+    if user_info.cv == 0 {
+        let error_message = "Insufficient collateral: cv is 0".to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 || amount > state_mut.config.max_loan_amount || amount > user_info.mla {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if state_mut.total_borrowed.saturating_add(amount) > state_mut.config.borrow_cap {
+        let error_message = ERROR_BORROW_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let previous_amount = user_info.stable_loan_amount;
+    let previous_rate = user_info.stable_rate;
+    let new_rate = state_mut.interest_rate.saturating_add(state_mut.config.stable_rate_premium);
+    let total_amount = previous_amount.saturating_add(amount);
+
+    // CEI: update loan state BEFORE the external transfer
+    user_info.stable_rate = previous_amount
+        .saturating_mul(previous_rate)
+        .saturating_add(amount.saturating_mul(new_rate))
+        .checked_div(total_amount)
+        .unwrap_or(new_rate);
+    user_info.stable_loan_amount = total_amount;
+    user_info.stable_borrow_last_updated = exec::block_timestamp() as u128;
+    state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(amount);
+
+    refresh_user_position(service, caller);
+    service.refresh_rates();
+
+    let result = service.transfer_tokens(exec::program_id(), caller, amount).await;
+
+    // Roll back state if the transfer fails
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        user_info.stable_loan_amount = previous_amount;
+        user_info.stable_rate = previous_rate;
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(amount);
+        refresh_user_position(service, caller);
+        service.refresh_rates();
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    let stable_rate = state_mut.users.get(&caller).unwrap().stable_rate;
+
+    service.notify_stable_loan_taken(amount, stable_rate);
+
+    Ok(())
+}
+
+// Repay stable-rate debt
+pub async fn pay_stable_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Calculate and apply accrued loan interest before payment
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    let state_mut = service.state_mut();
+
+    let user_info = match state_mut.users.get_mut(&caller) {
+        Some(user_info) => user_info,
+        None => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if amount == 0 || amount > user_info.stable_loan_amount {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let previous_rate = user_info.stable_rate;
+
+    // CEI: update state BEFORE the external transfer to prevent re-entrancy.
+    user_info.stable_loan_amount -= amount;
+    if user_info.stable_loan_amount == 0 {
+        user_info.stable_rate = 0;
+    }
+    state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(amount);
+
+    let result = service.transfer_tokens(caller, exec::program_id(), amount).await;
+
+    // If transfer fails, roll back state
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        user_info.stable_loan_amount = user_info.stable_loan_amount.saturating_add(amount);
+        user_info.stable_rate = previous_rate;
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    refresh_user_position(service, caller);
+    service.refresh_rates();
+
+    service.notify_stable_loan_payed(amount);
+
+    Ok(())
+}
+
+// Keeper methods
+// Callers are expected to have checked risk privileges already.
+
+// Reset the user's stable rate to the current variable rate plus the premium
+// when the two have drifted apart by more than the rebalance delta, in either direction.
+pub fn rebalance_stable_rate<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    user: ActorId,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    // Settle interest at the old rate first
+    let _ = service.calculate_loan_interest_rate_amount(user);
+
+    let state_mut = service.state_mut();
+    let current_rate = state_mut.interest_rate.saturating_add(state_mut.config.stable_rate_premium);
+    let rebalance_delta = state_mut.config.stable_rate_rebalance_delta;
+
+    let user_info = match state_mut.users.get_mut(&user) {
+        Some(user_info) if user_info.stable_loan_amount > 0 => user_info,
+        _ => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if user_info.stable_rate.abs_diff(current_rate) <= rebalance_delta {
+        let error_message = ERROR_STABLE_RATE_NOT_DIVERGED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    user_info.stable_rate = current_rate;
+
+    service.notify_stable_rate_rebalanced(user, current_rate);
+
+    Ok(())
+}
@@ -7,16 +7,22 @@ use sails_rs::{
     }
 };
 
+use sails_rs::gstd::calls::GStdArgs;
 use crate::clients::extended_vft_client::traits::Vft;
 use crate::services::vst_liquidity_injection::LiquidityInjectionService;
 use crate::services::vst_liquidity_injection::LiquidityEvent;
+use crate::services::isolation;
 use crate::services::utils::{
     EventNotifier,
     ERROR_TRANSFER_FAILED,
     ERROR_INVALID_AMOUNT,
     ERROR_USER_NOT_FOUND,
     ERROR_REWARDS_POOL_INSUFFICIENT,
-    ERROR_USER_REWARDS_INSUFFICIENT
+    ERROR_USER_REWARDS_INSUFFICIENT,
+    ERROR_SUPPLY_CAP_EXCEEDED,
+    ERROR_COLLATERAL_CAP_EXCEEDED,
+    ERROR_ISOLATED_COLLATERAL_MIXING,
+    ERROR_CDP_MODE_ACTIVE
 };
 
 // Public methods
@@ -27,17 +33,30 @@ pub async fn deposit_liquidity<VftClient>(
     amount: u128
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     service.update_all_rewards();
     let state_mut = service.state_mut();
 
+    if state_mut.cdp.is_some() {
+        let error_message = ERROR_CDP_MODE_ACTIVE.to_string();
+        service.notify_error(error_message.clone());
+        return sails_rs::Err(error_message);
+    }
+
     debug!("Depositing funds");
     if amount > state_mut.config.max_liquidity_deposit || amount == 0 {
         let error_message = ERROR_INVALID_AMOUNT.to_string();
         service.notify_error(error_message.clone());
         return sails_rs::Err(error_message);
     }
+
+    // Enforce the protocol-wide supply cap before pulling any tokens
+    if state_mut.total_deposited.saturating_add(amount) > state_mut.config.supply_cap {
+        let error_message = ERROR_SUPPLY_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return sails_rs::Err(error_message);
+    }
  
     let caller = msg::source();
     let decimals_factor = state_mut.config.decimals_factor;
@@ -105,7 +124,7 @@ pub async fn withdraw_liquidity<VftClient>(
     amount: u128
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     service.update_all_rewards();
 
@@ -202,7 +221,7 @@ pub async fn withdraw_rewards<VftClient>(
     service: &mut LiquidityInjectionService<VftClient>
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     service.update_all_rewards();
     service.update_all_collateral_available_to_withdraw();
@@ -327,12 +346,59 @@ pub async fn deposit_collateral<VftClient>(
     service: &mut LiquidityInjectionService<VftClient>
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
-    let state_mut = service.state_mut();
+    let caller = msg::source();
 
+    deposit_collateral_for(service, caller).await
+}
+
+// Deposit Vara as Collateral on behalf of another user
+// The attached VARA is credited to the borrower, topping up a position close to liquidation.
+pub async fn deposit_collateral_for<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    borrower: ActorId
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let depositor = msg::source();
     let value = msg::value();
-    let caller = msg::source();
+
+    let result = credit_vara_collateral(service, depositor, borrower, value);
+
+    if let Err(error_message) = &result {
+        refund_value(depositor, value, error_message);
+    }
+
+    result
+}
+
+// Return the VARA attached to a rejected message. Returning `Err` keeps the value
+// in the program, so it is sent back explicitly; if even that send fails the
+// message traps and the runtime returns it.
+pub(crate) fn refund_value(to: ActorId, value: u128, error_message: &str) {
+    if value == 0 {
+        return;
+    }
+
+    if msg::send(to, LiquidityEvent::Error(error_message.to_string()), value).is_err() {
+        panic!("{}", error_message);
+    }
+}
+
+// Credit `value` VARA already received by the program as the borrower's collateral
+pub(crate) fn credit_vara_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    depositor: ActorId,
+    borrower: ActorId,
+    value: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+
     let one_tvara = state_mut.config.one_tvara;
 
     if value == 0 {
@@ -349,10 +415,22 @@ where
         return Err(error_message);
     }
 
+    if state_mut.total_collateral_vara.saturating_add(value) > state_mut.config.collateral_cap {
+        let error_message = ERROR_COLLATERAL_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !isolation::can_add_collateral(state_mut, &borrower, None) {
+        let error_message = ERROR_ISOLATED_COLLATERAL_MIXING.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
     // Update user collateral
     let current_timestamp = exec::block_timestamp() as u128;
     let user_info = state_mut.users
-        .entry(caller)
+        .entry(borrower)
         .or_insert_with(|| LiquidityInjectionService::<VftClient>::create_new_user(current_timestamp));
 
     user_info.balance_vara = user_info
@@ -364,9 +442,11 @@ where
             error_message
         })?;
 
+    state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(value);
+
     //Update CV and MLA
-    service.calculate_cv(caller);
-    service.calculate_mla(caller);
+    service.calculate_cv(borrower);
+    service.calculate_mla(borrower);
 
     // Calculate available to withdraw vara
     LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
@@ -382,7 +462,11 @@ where
     service.refresh_rates();
 
     // Notify the deposit event
-    service.notify_deposited_vara(amount);
+    if depositor == borrower {
+        service.notify_deposited_vara(amount);
+    } else {
+        service.notify_collateral_deposited_for(depositor, borrower, amount);
+    }
 
     Ok(())
 }
@@ -393,7 +477,7 @@ pub async fn withdraw_collateral<VftClient>(
     amount: u128
 ) -> Result<(), String>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     let state_mut = service.state_mut();
     let caller = msg::source();
@@ -433,15 +517,25 @@ where
             error_message
         })?;
 
-    if let Err(_err) = msg::send(
-        caller,
-        LiquidityEvent::WithdrawnVara { amount: amount }, 
-        amount_vara
-    ) {
+    state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_sub(amount_vara);
+
+    // Inside a multicall the VARA is sent once all actions succeeded
+    let result = if service.defer_vara(amount_vara) {
+        Ok(())
+    } else {
+        msg::send(
+            caller,
+            LiquidityEvent::WithdrawnVara { amount: amount },
+            amount_vara
+        ).map(|_| ())
+    };
+
+    if let Err(_err) = result {
         // Roll back on send failure
         let state_mut = service.state_mut();
         let user_info = state_mut.users.get_mut(&caller).unwrap();
         user_info.balance_vara = user_info.balance_vara.saturating_add(amount_vara);
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(amount_vara);
         let error_message = ERROR_TRANSFER_FAILED.to_string();
         service.notify_error(error_message.clone());
         return sails_rs::Err(error_message);
@@ -456,7 +550,7 @@ where
     // Calculate available to withdraw vara
     LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
 
-    let _ = service.liquidate_user_loan(caller).await;
+    let _ = service.liquidate_loan(caller).await;
 
     service.refresh_rates();
 
@@ -0,0 +1,377 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::{LiquidityInjectionService, LiquidityEvent};
+use crate::services::{reserves, emode, isolation};
+use crate::states::vstreet_state::{VstreetState, TermLoan, Config};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT,
+    ERROR_USER_NOT_FOUND,
+    ERROR_BORROW_CAP_EXCEEDED,
+    ERROR_TERM_LOAN_NOT_FOUND,
+    ERROR_INVALID_LOAN_TERM,
+    ERROR_EARLY_PARTIAL_REPAYMENT,
+    ERROR_TERM_LOAN_NOT_OVERDUE,
+    ERROR_EMODE_ASSET_NOT_ALLOWED,
+    ERROR_ISOLATION_ASSET_NOT_ALLOWED
+};
+
+// Internal helpers
+
+// Apply interest at the loan's locked rate. Overdue loans keep accruing.
+pub fn accrue_term_loan(term_loan: &mut TermLoan, config: &Config, current_timestamp: u128) {
+    let time_elapsed = current_timestamp.saturating_sub(term_loan.last_updated) / 1000;
+
+    if time_elapsed == 0 {
+        return;
+    }
+
+    let denominator = config
+        .year_in_seconds
+        .saturating_mul(config.decimals_factor)
+        .saturating_mul(100);
+
+    let interest = term_loan
+        .amount
+        .saturating_mul(term_loan.rate)
+        .saturating_mul(time_elapsed)
+        .checked_div(denominator)
+        .unwrap_or(0);
+
+    term_loan.amount = term_loan.amount.saturating_add(interest);
+    term_loan.last_updated = current_timestamp;
+}
+
+pub fn accrue_user_term_loans(state: &mut VstreetState, user: &ActorId, current_timestamp: u128) {
+    let config = &state.config;
+
+    for term_loan in state.term_loans.values_mut() {
+        if term_loan.borrower == *user {
+            accrue_term_loan(term_loan, config, current_timestamp);
+        }
+    }
+}
+
+// Outstanding debt across all term loans of a user, in primary token units
+pub fn term_debt_value(state: &VstreetState, user: &ActorId) -> u128 {
+    state
+        .term_loans
+        .values()
+        .filter(|term_loan| term_loan.borrower == *user)
+        .fold(0, |total, term_loan| total.saturating_add(term_loan.amount))
+}
+
+// Debt drawn next to the variable primary loan: reserve loans, term loans and
+// stable-rate debt. Any of it keeps the position active and counts against the same MLA.
+pub fn secondary_debt_value(state: &VstreetState, user: &ActorId) -> u128 {
+    let stable_debt = state
+        .users
+        .get(user)
+        .map(|user_info| user_info.stable_loan_amount)
+        .unwrap_or(0);
+
+    reserves::reserve_debt_value(state, user)
+        .saturating_add(term_debt_value(state, user))
+        .saturating_add(stable_debt)
+}
+
+pub(crate) fn refresh_user_position<VftClient>(service: &mut LiquidityInjectionService<VftClient>, user: ActorId)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let has_secondary_debt = secondary_debt_value(state_mut, &user) > 0;
+
+    if let Some(user_info) = state_mut.users.get_mut(&user) {
+        user_info.is_loan_active = user_info.loan_amount > 0 || has_secondary_debt;
+    }
+
+    service.calculate_cv(user);
+    service.calculate_mla(user);
+    service.update_user_ltv(user);
+
+    if let Some(user_info) = state_mut.users.get_mut(&user) {
+        LiquidityInjectionService::<VftClient>::update_user_available_to_withdraw_vara(user_info);
+    }
+}
+
+// Public methods
+
+// Take a fixed-rate loan maturing `term` milliseconds from now.
+// The rate is the current variable rate plus the term premium, locked for the life of the loan.
+pub async fn take_term_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128,
+    term: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    // Refresh accrued interest BEFORE reading MLA
+    let _ = service.calculate_loan_interest_rate_amount(caller);
+
+    {
+        let state_mut = service.state_mut();
+        if !state_mut.users.contains_key(&caller) {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    }
+
+    let _ = service.calculate_mla(caller);
+    let state_mut = service.state_mut();
+
+    if !emode::can_borrow_primary(state_mut, &caller) {
+        let error_message = ERROR_EMODE_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if !isolation::can_borrow_primary(state_mut, &caller) {
+        let error_message = ERROR_ISOLATION_ASSET_NOT_ALLOWED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+
+    if term < state_mut.config.min_loan_term || term > state_mut.config.max_loan_term {
+        let error_message = ERROR_INVALID_LOAN_TERM.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if user_info.cv == 0 {
+        let error_message = "Insufficient collateral: cv is 0".to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 || amount > state_mut.config.max_loan_amount || amount > user_info.mla {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if state_mut.total_borrowed.saturating_add(amount) > state_mut.config.borrow_cap {
+        let error_message = ERROR_BORROW_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let current_timestamp = exec::block_timestamp() as u128;
+    let rate = state_mut.interest_rate.saturating_add(state_mut.config.term_rate_premium);
+    let maturity = current_timestamp.saturating_add(term);
+    let loan_id = state_mut.next_term_loan_id;
+
+    // CEI: record the loan BEFORE the external transfer
+    state_mut.next_term_loan_id = loan_id.saturating_add(1);
+    state_mut.term_loans.insert(
+        loan_id,
+        TermLoan {
+            borrower: caller,
+            principal: amount,
+            amount,
+            rate,
+            start: current_timestamp,
+            maturity,
+            last_updated: current_timestamp,
+        },
+    );
+    state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(amount);
+
+    refresh_user_position(service, caller);
+    service.refresh_rates();
+
+    let result = service.transfer_tokens(exec::program_id(), caller, amount).await;
+
+    // Roll back state if the transfer fails
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        state_mut.term_loans.remove(&loan_id);
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(amount);
+        refresh_user_position(service, caller);
+        service.refresh_rates();
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.notify_term_loan_taken(loan_id, amount, rate, maturity);
+
+    Ok(())
+}
+
+// Repay a term loan. Before maturity only full repayment is accepted and it
+// carries the early repayment fee, from maturity on partial repayments are free.
+// Interest is settled before principal.
+pub async fn repay_term_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    loan_id: u64,
+    amount: u128,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+    let state_mut = service.state_mut();
+    let current_timestamp = exec::block_timestamp() as u128;
+
+    let term_loan = match state_mut.term_loans.get_mut(&loan_id) {
+        Some(term_loan) if term_loan.borrower == caller => term_loan,
+        _ => {
+            let error_message = ERROR_TERM_LOAN_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    accrue_term_loan(term_loan, &state_mut.config, current_timestamp);
+
+    if amount == 0 || amount > term_loan.amount {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let fee = if current_timestamp < term_loan.maturity {
+        if amount != term_loan.amount {
+            let error_message = ERROR_EARLY_PARTIAL_REPAYMENT.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+        amount.saturating_mul(state_mut.config.early_repayment_fee) / 100
+    } else {
+        0
+    };
+
+    let snapshot = term_loan.clone();
+    let interest_due = term_loan.amount.saturating_sub(term_loan.principal);
+    let principal_paid = amount.saturating_sub(interest_due);
+
+    // CEI: update state BEFORE the external transfer to prevent re-entrancy.
+    term_loan.amount -= amount;
+    term_loan.principal = term_loan.principal.saturating_sub(principal_paid);
+    if term_loan.amount == 0 {
+        state_mut.term_loans.remove(&loan_id);
+    }
+    state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(principal_paid);
+    // The early repayment fee goes to suppliers
+    state_mut.available_rewards_pool = state_mut.available_rewards_pool.saturating_add(fee);
+
+    let result = service.transfer_tokens(caller, exec::program_id(), amount.saturating_add(fee)).await;
+
+    // If transfer fails, roll back state
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        state_mut.term_loans.insert(loan_id, snapshot);
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(principal_paid);
+        state_mut.available_rewards_pool = state_mut.available_rewards_pool.saturating_sub(fee);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    refresh_user_position(service, caller);
+    service.refresh_rates();
+
+    service.notify_term_loan_payed(loan_id, amount);
+
+    Ok(())
+}
+
+// Liquidate an overdue term loan regardless of LTV. Anyone can trigger it.
+// VARA collateral worth the outstanding amount goes to the protocol owner,
+// any shortfall the collateral cannot cover is written off.
+pub async fn liquidate_overdue_term_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    loan_id: u64,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let current_timestamp = exec::block_timestamp() as u128;
+    let owner = state_mut.owner;
+
+    let term_loan = match state_mut.term_loans.get_mut(&loan_id) {
+        Some(term_loan) => term_loan,
+        None => {
+            let error_message = ERROR_TERM_LOAN_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if current_timestamp < term_loan.maturity {
+        let error_message = ERROR_TERM_LOAN_NOT_OVERDUE.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    accrue_term_loan(term_loan, &state_mut.config, current_timestamp);
+
+    let snapshot = term_loan.clone();
+    let user = snapshot.borrower;
+    let loan_amount = snapshot.amount;
+
+    let debt_in_vara = loan_amount
+        .saturating_mul(state_mut.config.one_tvara)
+        .checked_div(state_mut.config.vara_price)
+        .unwrap_or(0);
+
+    let user_info = match state_mut.users.get_mut(&user) {
+        Some(user_info) => user_info,
+        None => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    let seized = debt_in_vara.min(user_info.balance_vara);
+
+    // CEI: close the loan and seize collateral BEFORE sending VARA
+    user_info.balance_vara -= seized;
+    state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_sub(seized);
+    state_mut.term_loans.remove(&loan_id);
+    state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(snapshot.principal);
+
+    refresh_user_position(service, user);
+
+    if seized > 0 && msg::send(
+        owner,
+        LiquidityEvent::TermLoanLiquidated { loan_id, user, loan_amount, collateral_seized: seized },
+        seized,
+    ).is_err() {
+        // Roll back user state if the VARA transfer fails
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&user).unwrap();
+        user_info.balance_vara = user_info.balance_vara.saturating_add(seized);
+        state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(seized);
+        state_mut.term_loans.insert(loan_id, snapshot.clone());
+        state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(snapshot.principal);
+        refresh_user_position(service, user);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.refresh_rates();
+
+    service.notify_term_loan_liquidated(loan_id, user, loan_amount, seized);
+
+    Ok(())
+}
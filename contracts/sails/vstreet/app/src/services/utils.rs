@@ -10,6 +10,65 @@ pub const ERROR_USER_REWARDS_INSUFFICIENT: &str = "Not enough rewards to withdra
 pub const ERROR_INSUFFICIENT_ADMIN_PRIVILEGES: &str = "Only an administrator can perform this action";
 pub const ERROR_ADMIN_ALREADY_EXISTS: &str = "Admin already exists";
 pub const ERROR_ADMIN_DOESNT_EXIST: &str = "Admin does not exist";
+pub const ERROR_INSUFFICIENT_RISK_PRIVILEGES: &str = "Only a risk manager can perform this action";
+pub const ERROR_RISK_MANAGER_ALREADY_EXISTS: &str = "Risk manager already exists";
+pub const ERROR_RISK_MANAGER_DOESNT_EXIST: &str = "Risk manager does not exist";
+pub const ERROR_SUPPLY_CAP_EXCEEDED: &str = "Supply cap exceeded";
+pub const ERROR_BORROW_CAP_EXCEEDED: &str = "Borrow cap exceeded";
+pub const ERROR_COLLATERAL_CAP_EXCEEDED: &str = "Collateral cap exceeded";
+pub const ERROR_DELEGATION_ALLOWANCE_EXCEEDED: &str = "Delegation allowance exceeded";
+pub const ERROR_INVALID_DELEGATEE: &str = "Invalid delegatee";
+pub const ERROR_COLLATERAL_ASSET_NOT_FOUND: &str = "Collateral asset not found";
+pub const ERROR_COLLATERAL_ASSET_ALREADY_EXISTS: &str = "Collateral asset already exists";
+pub const ERROR_COLLATERAL_ASSET_DISABLED: &str = "Collateral asset is disabled";
+pub const ERROR_INVALID_COLLATERAL_PARAMS: &str = "Invalid collateral parameters";
+pub const ERROR_INSUFFICIENT_COLLATERAL: &str = "Insufficient collateral for outstanding loan";
+pub const ERROR_RESERVE_NOT_FOUND: &str = "Reserve not found";
+pub const ERROR_RESERVE_ALREADY_EXISTS: &str = "Reserve already exists";
+pub const ERROR_RESERVE_DISABLED: &str = "Reserve is disabled";
+pub const ERROR_RESERVE_INSUFFICIENT_LIQUIDITY: &str = "Not enough liquidity in the reserve";
+pub const ERROR_TERM_LOAN_NOT_FOUND: &str = "Term loan not found";
+pub const ERROR_INVALID_LOAN_TERM: &str = "Loan term out of range";
+pub const ERROR_EARLY_PARTIAL_REPAYMENT: &str = "Term loans can only be repaid in full before maturity";
+pub const ERROR_TERM_LOAN_NOT_OVERDUE: &str = "Term loan is not overdue";
+pub const ERROR_STABLE_RATE_NOT_DIVERGED: &str = "Stable rate is within the rebalance delta";
+pub const ERROR_MARKET_NOT_FOUND: &str = "Market not found";
+pub const ERROR_MARKET_ALREADY_EXISTS: &str = "Market already exists";
+pub const ERROR_INVALID_LTV: &str = "LTV must be between 1 and 95";
+pub const ERROR_EMODE_CATEGORY_NOT_FOUND: &str = "E-mode category not found";
+pub const ERROR_INVALID_EMODE_PARAMS: &str = "Invalid e-mode category parameters";
+pub const ERROR_EMODE_DEBT_OUTSIDE_CATEGORY: &str = "Outstanding debt is outside the e-mode category";
+pub const ERROR_EMODE_ASSET_NOT_ALLOWED: &str = "Asset cannot be borrowed in the current e-mode category";
+pub const ERROR_ISOLATED_COLLATERAL_MIXING: &str = "Isolated collateral cannot be mixed with other collateral";
+pub const ERROR_ISOLATION_ASSET_NOT_ALLOWED: &str = "Asset cannot be borrowed against isolated collateral";
+pub const ERROR_CDP_NOT_ENABLED: &str = "CDP mode is not enabled";
+pub const ERROR_CDP_MODE_ACTIVE: &str = "Supplier liquidity is disabled in CDP mode";
+pub const ERROR_CDP_OUTSTANDING_DEBT: &str = "Stablecoin cannot change while debt is outstanding";
+pub const ERROR_CDP_DEBT_CEILING_EXCEEDED: &str = "Vault debt ceiling exceeded";
+pub const ERROR_VAULT_NOT_LIQUIDATABLE: &str = "Vault is not liquidatable";
+pub const ERROR_VAULT_LIQUIDATION_REQUIRED: &str = "Vaults are liquidated with LiquidateVault in CDP mode";
+pub const ERROR_GOVERNANCE_NOT_CONFIGURED: &str = "Governance token not configured";
+pub const ERROR_GOVERNANCE_TOKENS_LOCKED: &str = "Governance token cannot change while tokens are locked";
+pub const ERROR_PROPOSAL_NOT_FOUND: &str = "Proposal not found";
+pub const ERROR_PROPOSAL_THRESHOLD: &str = "Locked balance is below the proposal threshold";
+pub const ERROR_PROPOSAL_NOT_ACTIVE: &str = "Proposal is not active";
+pub const ERROR_VOTING_CLOSED: &str = "Voting is closed";
+pub const ERROR_VOTING_ACTIVE: &str = "Voting is still active";
+pub const ERROR_ALREADY_VOTED: &str = "Already voted";
+pub const ERROR_NO_VOTING_POWER: &str = "No voting power at the proposal snapshot";
+pub const ERROR_PROPOSAL_NOT_QUEUED: &str = "Proposal is not queued";
+pub const ERROR_TIMELOCK_NOT_EXPIRED: &str = "Timelock has not expired";
+pub const ERROR_SECONDARY_DEBT_OUTSTANDING: &str = "Repay reserve, term and stable loans first";
+pub const ERROR_SWAP_VENUE_NOT_CONFIGURED: &str = "Swap venue not configured";
+pub const ERROR_INVALID_TARGET_LTV: &str = "Target LTV is out of range";
+pub const ERROR_SWAP_FAILED: &str = "Swap failed";
+pub const ERROR_INVALID_SWAP_PAIR: &str = "Cannot swap collateral into the same asset";
+pub const ERROR_INVALID_WARNING_BAND: &str = "Warning band must be at most 100";
+pub const ERROR_UNHEALTHY_POSITION: &str = "Health factor would fall below 1";
+pub const ERROR_OPERATION_IN_PROGRESS: &str = "Another operation is in progress for this user";
+pub const ERROR_PROTOCOL_BUSY: &str = "A batch job is in progress, try again later";
+pub const ERROR_PENDING_OPERATION_NOT_FOUND: &str = "No recovered operation with this id";
+pub const ERROR_SIGNAL_GAS_NOT_RESERVED: &str = "Not enough gas to reserve for recovery";
 
 pub trait EventNotifier {
     fn notify_deposit(&mut self, amount: u128);
@@ -23,4 +82,58 @@ pub trait EventNotifier {
     fn notify_withdrawn_vara(&mut self, amount: u128);
     fn notify_loan_taken(&mut self, amount: u128);
     fn notify_loan_payed(&mut self, amount: u128);
+    fn notify_loan_repaid_for(&mut self, payer: ActorId, borrower: ActorId, amount: u128);
+    fn notify_collateral_deposited_for(&mut self, depositor: ActorId, borrower: ActorId, amount: u128);
+    fn notify_delegation_approved(&mut self, delegator: ActorId, delegatee: ActorId, amount: u128);
+    fn notify_loan_taken_on_behalf(&mut self, delegator: ActorId, delegatee: ActorId, amount: u128);
+    fn notify_caps_updated(&mut self, supply_cap: u128, borrow_cap: u128, collateral_cap: u128);
+    fn notify_collateral_asset_listed(&mut self, asset: ActorId);
+    fn notify_collateral_asset_updated(&mut self, asset: ActorId);
+    fn notify_collateral_price_updated(&mut self, asset: ActorId, price: u128);
+    fn notify_deposited_token_collateral(&mut self, asset: ActorId, amount: u128);
+    fn notify_withdrawn_token_collateral(&mut self, asset: ActorId, amount: u128);
+    fn notify_reserve_updated(&mut self, asset: ActorId);
+    fn notify_reserve_deposit(&mut self, asset: ActorId, amount: u128);
+    fn notify_reserve_withdraw(&mut self, asset: ActorId, amount: u128);
+    fn notify_reserve_loan_taken(&mut self, asset: ActorId, amount: u128);
+    fn notify_reserve_loan_payed(&mut self, asset: ActorId, amount: u128);
+    fn notify_vara_pool_updated(&mut self);
+    fn notify_vara_pool_deposit(&mut self, amount: u128);
+    fn notify_vara_pool_withdraw(&mut self, amount: u128);
+    fn notify_deposited_stable_collateral(&mut self, amount: u128);
+    fn notify_withdrawn_stable_collateral(&mut self, amount: u128);
+    fn notify_vara_loan_taken(&mut self, amount: u128);
+    fn notify_vara_loan_payed(&mut self, amount: u128);
+    fn notify_vara_loan_liquidated(&mut self, user: ActorId, loan_amount: u128, collateral_seized: u128);
+    fn notify_term_loan_taken(&mut self, loan_id: u64, amount: u128, rate: u128, maturity: u128);
+    fn notify_term_loan_payed(&mut self, loan_id: u64, amount: u128);
+    fn notify_term_loan_liquidated(&mut self, loan_id: u64, user: ActorId, loan_amount: u128, collateral_seized: u128);
+    fn notify_stable_loan_taken(&mut self, amount: u128, stable_rate: u128);
+    fn notify_stable_loan_payed(&mut self, amount: u128);
+    fn notify_stable_rate_rebalanced(&mut self, user: ActorId, stable_rate: u128);
+    fn notify_emode_category_updated(&mut self, category_id: u8);
+    fn notify_user_emode_set(&mut self, user: ActorId, category_id: u8);
+    fn notify_cdp_updated(&mut self, stablecoin: ActorId, stability_fee: u128, debt_ceiling: u128);
+    fn notify_vault_liquidated(&mut self, user: ActorId, liquidator: ActorId, debt: u128, collateral_seized: u128);
+    fn notify_savings_rate_updated(&mut self, rate: u128);
+    fn notify_savings_deposit(&mut self, amount: u128);
+    fn notify_savings_withdraw(&mut self, amount: u128);
+    fn notify_governance_updated(&mut self, token: ActorId);
+    fn notify_governance_tokens_locked(&mut self, amount: u128);
+    fn notify_governance_tokens_unlocked(&mut self, amount: u128);
+    fn notify_proposal_created(&mut self, proposal_id: u64, proposer: ActorId);
+    fn notify_vote_cast(&mut self, proposal_id: u64, voter: ActorId, support: bool, weight: u128);
+    fn notify_proposal_queued(&mut self, proposal_id: u64, eta: u128);
+    fn notify_proposal_executed(&mut self, proposal_id: u64);
+    fn notify_proposal_canceled(&mut self, proposal_id: u64);
+    fn notify_multicall_executed(&mut self, actions: u32);
+    fn notify_position_closed(&mut self, debt_repaid: u128, collateral_returned: u128, rewards_claimed: u128);
+    fn notify_swap_venue_updated(&mut self, venue: ActorId, max_slippage: u128);
+    fn notify_leveraged(&mut self, iterations: u32, borrowed: u128, collateral_added: u128);
+    fn notify_deleveraged(&mut self, iterations: u32, collateral_sold: u128, debt_repaid: u128);
+    fn notify_collateral_swapped(&mut self, from_asset: ActorId, to_asset: ActorId, amount_in: u128, amount_out: u128);
+    fn notify_self_liquidated(&mut self, user: ActorId, debt_repaid: u128, collateral_sold: u128, fee: u128);
+    fn notify_position_at_risk(&mut self, user: ActorId, ltv: u128, threshold: u128);
+    fn notify_risk_warning_band_updated(&mut self, warning_band: u128);
+    fn notify_pending_operation_cleared(&mut self, operation_id: MessageId);
 }
\ No newline at end of file
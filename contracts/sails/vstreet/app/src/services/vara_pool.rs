@@ -0,0 +1,491 @@
+use sails_rs::{
+    prelude::*,
+    gstd::{
+        msg,
+        exec,
+    }
+};
+
+use sails_rs::gstd::calls::GStdArgs;
+use crate::clients::extended_vft_client::traits::Vft;
+use crate::services::vst_liquidity_injection::{LiquidityInjectionService, LiquidityEvent};
+use crate::services::supply;
+use crate::services::reserves::{accrue_reserve, refresh_reserve_rates};
+use crate::services::utils::{
+    EventNotifier,
+    ERROR_TRANSFER_FAILED,
+    ERROR_INVALID_AMOUNT,
+    ERROR_USER_NOT_FOUND,
+    ERROR_RESERVE_DISABLED,
+    ERROR_RESERVE_INSUFFICIENT_LIQUIDITY,
+    ERROR_SUPPLY_CAP_EXCEEDED,
+    ERROR_BORROW_CAP_EXCEEDED,
+    ERROR_INSUFFICIENT_COLLATERAL
+};
+
+// The VARA pool is the inverse of the primary pool: VARA suppliers fund it and
+// borrowers post the primary stable VFT as collateral. It reuses the reserve
+// rate model and the CV/MLA/LTV machinery with the VARA price inverted.
+
+// Internal helpers
+
+fn accrue_vara_pool<VftClient>(service: &mut LiquidityInjectionService<VftClient>)
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let state_mut = service.state_mut();
+    let current_timestamp = exec::block_timestamp() as u128;
+
+    accrue_reserve(&mut state_mut.vara_pool, &state_mut.config, current_timestamp);
+    refresh_reserve_rates(&mut state_mut.vara_pool, &state_mut.config);
+}
+
+fn vara_loan_of<VftClient>(service: &LiquidityInjectionService<VftClient>, user: &ActorId) -> u128
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    service
+        .state_mut()
+        .vara_pool
+        .positions
+        .get(user)
+        .map(|position| position.loan_amount)
+        .unwrap_or(0)
+}
+
+// Admin methods
+// Callers are expected to have checked admin privileges already.
+
+pub fn update_vara_pool<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    base_rate: u128,
+    risk_multiplier: u128,
+    dev_fee: u128,
+    supply_cap: u128,
+    borrow_cap: u128,
+    enabled: bool,
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    // Settle interest at the old rates before switching the model
+    accrue_vara_pool(service);
+
+    let state_mut = service.state_mut();
+    let vara_pool = &mut state_mut.vara_pool;
+
+    vara_pool.base_rate = base_rate;
+    vara_pool.risk_multiplier = risk_multiplier;
+    vara_pool.dev_fee = dev_fee;
+    vara_pool.supply_cap = supply_cap;
+    vara_pool.borrow_cap = borrow_cap;
+    vara_pool.enabled = enabled;
+
+    refresh_reserve_rates(vara_pool, &state_mut.config);
+
+    Ok(())
+}
+
+// Supply methods
+
+// Deposit native VARA into the VARA pool
+pub async fn deposit_vara_liquidity<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    accrue_vara_pool(service);
+
+    let state_mut = service.state_mut();
+    let value = msg::value();
+    let caller = msg::source();
+
+    if !state_mut.vara_pool.enabled {
+        let error_message = ERROR_RESERVE_DISABLED.to_string();
+        service.notify_error(error_message.clone());
+        supply::refund_value(caller, value, &error_message);
+        return Err(error_message);
+    }
+
+    if value == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if state_mut.vara_pool.total_deposited.saturating_add(value) > state_mut.vara_pool.supply_cap {
+        let error_message = ERROR_SUPPLY_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        supply::refund_value(caller, value, &error_message);
+        return Err(error_message);
+    }
+
+    let position = state_mut.vara_pool.positions.entry(caller).or_default();
+    position.balance = position.balance.saturating_add(value);
+    state_mut.vara_pool.total_deposited = state_mut.vara_pool.total_deposited.saturating_add(value);
+
+    refresh_reserve_rates(&mut state_mut.vara_pool, &state_mut.config);
+
+    service.notify_vara_pool_deposit(value);
+
+    Ok(())
+}
+
+// Withdraw native VARA (principal plus credited interest) from the VARA pool
+pub async fn withdraw_vara_liquidity<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    accrue_vara_pool(service);
+
+    let state_mut = service.state_mut();
+    let caller = msg::source();
+
+    let balance = state_mut.vara_pool.positions.get(&caller).map(|position| position.balance).unwrap_or(0);
+
+    if amount == 0 || amount > balance {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Only idle liquidity can leave the pool
+    if amount > state_mut.vara_pool.total_deposited.saturating_sub(state_mut.vara_pool.total_borrowed) {
+        let error_message = ERROR_RESERVE_INSUFFICIENT_LIQUIDITY.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: debit BEFORE sending VARA to prevent re-entrancy.
+    let position = state_mut.vara_pool.positions.get_mut(&caller).unwrap();
+    position.balance = position.balance.saturating_sub(amount);
+    state_mut.vara_pool.total_deposited = state_mut.vara_pool.total_deposited.saturating_sub(amount);
+
+    if let Err(_err) = msg::send(
+        caller,
+        LiquidityEvent::VaraPoolWithdraw { amount },
+        amount
+    ) {
+        // Roll back on send failure
+        let state_mut = service.state_mut();
+        let position = state_mut.vara_pool.positions.entry(caller).or_default();
+        position.balance = position.balance.saturating_add(amount);
+        state_mut.vara_pool.total_deposited = state_mut.vara_pool.total_deposited.saturating_add(amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    refresh_reserve_rates(&mut state_mut.vara_pool, &state_mut.config);
+
+    service.notify_vara_pool_withdraw(amount);
+
+    Ok(())
+}
+
+// Deposit the primary stable VFT as collateral for VARA loans
+pub async fn deposit_stable_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    let caller = msg::source();
+
+    if amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // Transfer tokens from user to contract
+    let result = service.transfer_tokens(caller, exec::program_id(), amount).await;
+
+    if result.is_err() {
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    let current_timestamp = exec::block_timestamp() as u128;
+    let user_info = state_mut.users
+        .entry(caller)
+        .or_insert_with(|| LiquidityInjectionService::<VftClient>::create_new_user(current_timestamp));
+
+    user_info.stable_collateral = user_info.stable_collateral.saturating_add(amount);
+    state_mut.total_stable_collateral = state_mut.total_stable_collateral.saturating_add(amount);
+
+    service.calculate_inverse_position(caller);
+
+    service.notify_deposited_stable_collateral(amount);
+
+    Ok(())
+}
+
+// Withdraw stable collateral; the rest must still cover the VARA loan
+pub async fn withdraw_stable_collateral<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    accrue_vara_pool(service);
+
+    let caller = msg::source();
+    let vara_loan = vara_loan_of(service, &caller);
+    let state_mut = service.state_mut();
+    let ltv = state_mut.ltv;
+    let vara_price = state_mut.config.vara_price;
+    let one_tvara = state_mut.config.one_tvara;
+
+    let user_info = match state_mut.users.get_mut(&caller) {
+        Some(user_info) => user_info,
+        None => {
+            let error_message = ERROR_USER_NOT_FOUND.to_string();
+            service.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+    };
+
+    if amount == 0 || amount > user_info.stable_collateral {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let remaining_cv = LiquidityInjectionService::<VftClient>::inverse_collateral_value(
+        user_info.stable_collateral - amount,
+        vara_price,
+        one_tvara,
+    );
+
+    if vara_loan > remaining_cv.saturating_mul(ltv) / 100 {
+        let error_message = ERROR_INSUFFICIENT_COLLATERAL.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: debit collateral BEFORE the external transfer to prevent re-entrancy.
+    user_info.stable_collateral -= amount;
+    state_mut.total_stable_collateral = state_mut.total_stable_collateral.saturating_sub(amount);
+
+    let result = service.transfer_tokens(exec::program_id(), caller, amount).await;
+
+    if result.is_err() {
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&caller).unwrap();
+        user_info.stable_collateral = user_info.stable_collateral.saturating_add(amount);
+        state_mut.total_stable_collateral = state_mut.total_stable_collateral.saturating_add(amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.calculate_inverse_position(caller);
+
+    service.notify_withdrawn_stable_collateral(amount);
+
+    Ok(())
+}
+
+// Borrow methods
+
+// Borrow native VARA against stable collateral
+pub async fn take_vara_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    amount: u128
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    // Refresh accrued interest BEFORE reading MLA
+    accrue_vara_pool(service);
+
+    let caller = msg::source();
+    let state_mut = service.state_mut();
+
+    if !state_mut.users.contains_key(&caller) {
+        let error_message = ERROR_USER_NOT_FOUND.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    service.calculate_inverse_position(caller);
+
+    let vara_pool = &mut state_mut.vara_pool;
+    let user_info = state_mut.users.get_mut(&caller).unwrap();
+
+    if !vara_pool.enabled {
+        let error_message = ERROR_RESERVE_DISABLED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if user_info.stable_cv == 0 {
+        let error_message = "Insufficient collateral: stable cv is 0".to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount == 0 || amount > user_info.vara_mla {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if vara_pool.total_borrowed.saturating_add(amount) > vara_pool.borrow_cap {
+        let error_message = ERROR_BORROW_CAP_EXCEEDED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    if amount > vara_pool.total_deposited.saturating_sub(vara_pool.total_borrowed) {
+        let error_message = ERROR_RESERVE_INSUFFICIENT_LIQUIDITY.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    // CEI: update loan state BEFORE sending VARA.
+    let position = vara_pool.positions.entry(caller).or_default();
+    position.loan_amount = position.loan_amount.saturating_add(amount);
+    vara_pool.total_borrowed = vara_pool.total_borrowed.saturating_add(amount);
+
+    if let Err(_err) = msg::send(
+        caller,
+        LiquidityEvent::VaraLoanTaken { amount },
+        amount
+    ) {
+        // Roll back on send failure
+        let state_mut = service.state_mut();
+        if let Some(position) = state_mut.vara_pool.positions.get_mut(&caller) {
+            position.loan_amount = position.loan_amount.saturating_sub(amount);
+        }
+        state_mut.vara_pool.total_borrowed = state_mut.vara_pool.total_borrowed.saturating_sub(amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    refresh_reserve_rates(&mut state_mut.vara_pool, &state_mut.config);
+    service.calculate_inverse_position(caller);
+
+    service.notify_vara_loan_taken(amount);
+
+    Ok(())
+}
+
+// Repay a VARA loan with the attached value. Any excess is sent back.
+pub async fn pay_vara_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    accrue_vara_pool(service);
+
+    let caller = msg::source();
+    let value = msg::value();
+    let loan_amount = vara_loan_of(service, &caller);
+
+    if value == 0 || loan_amount == 0 {
+        let error_message = ERROR_INVALID_AMOUNT.to_string();
+        service.notify_error(error_message.clone());
+        supply::refund_value(caller, value, &error_message);
+        return Err(error_message);
+    }
+
+    let repaid = value.min(loan_amount);
+    let excess = value - repaid;
+
+    let state_mut = service.state_mut();
+    let position = state_mut.vara_pool.positions.get_mut(&caller).unwrap();
+    position.loan_amount -= repaid;
+    state_mut.vara_pool.total_borrowed = state_mut.vara_pool.total_borrowed.saturating_sub(repaid);
+
+    if excess > 0 {
+        if let Err(_err) = msg::send(caller, LiquidityEvent::VaraLoanPayed { amount: repaid }, excess) {
+            // The repayment stands, the excess stays claimable as pool liquidity
+            let position = state_mut.vara_pool.positions.get_mut(&caller).unwrap();
+            position.balance = position.balance.saturating_add(excess);
+            state_mut.vara_pool.total_deposited = state_mut.vara_pool.total_deposited.saturating_add(excess);
+        }
+    }
+
+    refresh_reserve_rates(&mut state_mut.vara_pool, &state_mut.config);
+    service.calculate_inverse_position(caller);
+
+    service.notify_vara_loan_payed(repaid);
+
+    Ok(())
+}
+
+// Liquidate a VARA loan whose LTV reached the protocol LTV.
+// The matching share of stable collateral goes to the protocol owner.
+pub async fn liquidate_vara_loan<VftClient>(
+    service: &mut LiquidityInjectionService<VftClient>,
+    user: ActorId
+) -> Result<(), String>
+where
+    VftClient: Vft<Args = GStdArgs>,
+{
+    accrue_vara_pool(service);
+    service.calculate_inverse_position(user);
+
+    let loan_amount = vara_loan_of(service, &user);
+    let state_mut = service.state_mut();
+    let owner = state_mut.owner;
+    let protocol_ltv = state_mut.ltv;
+
+    let user_info = match state_mut.users.get_mut(&user) {
+        Some(user_info) => user_info,
+        None => return Ok(()),
+    };
+
+    let seized = user_info.stable_collateral.saturating_mul(user_info.vara_ltv) / 100;
+
+    if loan_amount == 0 || user_info.vara_ltv < protocol_ltv || seized == 0 {
+        return Ok(());
+    }
+
+    // CEI: close the loan and seize collateral BEFORE the external transfer
+    user_info.stable_collateral = user_info.stable_collateral.saturating_sub(seized);
+    state_mut.total_stable_collateral = state_mut.total_stable_collateral.saturating_sub(seized);
+    if let Some(position) = state_mut.vara_pool.positions.get_mut(&user) {
+        position.loan_amount = 0;
+    }
+    state_mut.vara_pool.total_borrowed = state_mut.vara_pool.total_borrowed.saturating_sub(loan_amount);
+
+    let result = service.transfer_tokens(exec::program_id(), owner, seized).await;
+
+    if result.is_err() {
+        // Roll back user state if the transfer fails
+        let state_mut = service.state_mut();
+        let user_info = state_mut.users.get_mut(&user).unwrap();
+        user_info.stable_collateral = user_info.stable_collateral.saturating_add(seized);
+        state_mut.total_stable_collateral = state_mut.total_stable_collateral.saturating_add(seized);
+        if let Some(position) = state_mut.vara_pool.positions.get_mut(&user) {
+            position.loan_amount = loan_amount;
+        }
+        state_mut.vara_pool.total_borrowed = state_mut.vara_pool.total_borrowed.saturating_add(loan_amount);
+        let error_message = ERROR_TRANSFER_FAILED.to_string();
+        service.notify_error(error_message.clone());
+        return Err(error_message);
+    }
+
+    let state_mut = service.state_mut();
+    refresh_reserve_rates(&mut state_mut.vara_pool, &state_mut.config);
+    service.calculate_inverse_position(user);
+
+    service.notify_vara_loan_liquidated(user, loan_amount, seized);
+
+    Ok(())
+}
@@ -1,27 +1,56 @@
-use sails_rs::calls::Call;
+use sails_rs::calls::{Call, Action as _};
 use sails_rs::{
     prelude::*,
     gstd::{
         msg,
         debug,
         exec,
+        calls::GStdArgs,
     }
 };
 use sails_rs::collections::BTreeMap;
+use core::ptr::addr_of_mut;
 
 use crate::clients::extended_vft_client::traits::Vft;
-use crate::states::vstreet_state::{VstreetState, UserInfo, Config};
-use crate::services::{supply, borrow};
+use crate::states::vstreet_state::{VstreetState, UserInfo, Config, CollateralAsset, Reserve, ReserveParams, Savings, Governance, RiskWatch, OperationLocks, ConfigChange, Action};
+use crate::services::{supply, borrow, collateral, reserves, vara_pool, term_loans, stable_rate, emode, isolation, cdp, savings, governance, multicall, positions, leverage, dex, collateral_swap, risk, preview, locks, recovery};
+use crate::services::multicall::Settlement;
 use crate::services::utils::{
     EventNotifier,
     ERROR_INSUFFICIENT_ADMIN_PRIVILEGES,
     ERROR_ADMIN_ALREADY_EXISTS,
     ERROR_ADMIN_DOESNT_EXIST,
+    ERROR_INSUFFICIENT_RISK_PRIVILEGES,
+    ERROR_RISK_MANAGER_ALREADY_EXISTS,
+    ERROR_RISK_MANAGER_DOESNT_EXIST,
+    ERROR_MARKET_NOT_FOUND,
+    ERROR_MARKET_ALREADY_EXISTS,
+    ERROR_INVALID_LTV,
+    ERROR_VAULT_LIQUIDATION_REQUIRED,
     ERROR_TRANSFER_FAILED
 };
 
 static mut VSTREET_STATE: Option<VstreetState> = None;
 
+// Isolated markets, each with its own borrow asset, collateral, config and users
+static mut MARKETS: BTreeMap<u32, VstreetState> = BTreeMap::new();
+
+fn isolated_markets() -> &'static mut BTreeMap<u32, VstreetState> {
+    unsafe { &mut *addr_of_mut!(MARKETS) }
+}
+
+// State of the default market or of an isolated one. Reply and signal hooks run
+// without a service, so they reach the state through here.
+pub(crate) fn market_state(market_id: Option<u32>) -> &'static mut VstreetState {
+    if let Some(market_id) = market_id {
+        return isolated_markets().get_mut(&market_id).expect(ERROR_MARKET_NOT_FOUND);
+    }
+
+    let state = unsafe { VSTREET_STATE.as_mut() };
+    debug_assert!(state.is_some(), "state is not started!");
+    unsafe { state.unwrap_unchecked() }
+}
+
 #[derive(Decode, Encode, TypeInfo)]
 pub enum LiquidityEvent {
     Deposit{amount:u128},
@@ -36,15 +65,76 @@ pub enum LiquidityEvent {
     LoanTaken{amount:u128},
     LoanPayed{amount:u128},
     LoanLiquidated{user:ActorId, loan_amount:u128, collateral_seized:u128},
+    LoanRepaidFor{payer:ActorId, borrower:ActorId, amount:u128},
+    CollateralDepositedFor{depositor:ActorId, borrower:ActorId, amount:u128},
+    DelegationApproved{delegator:ActorId, delegatee:ActorId, amount:u128},
+    LoanTakenOnBehalf{delegator:ActorId, delegatee:ActorId, amount:u128},
+    CapsUpdated{supply_cap:u128, borrow_cap:u128, collateral_cap:u128},
+    CollateralAssetListed{asset:ActorId},
+    CollateralAssetUpdated{asset:ActorId},
+    CollateralPriceUpdated{asset:ActorId, price:u128},
+    DepositedTokenCollateral{asset:ActorId, amount:u128},
+    WithdrawnTokenCollateral{asset:ActorId, amount:u128},
+    TokenCollateralSeized{user:ActorId, asset:ActorId, amount:u128},
+    ReserveUpdated{asset:ActorId},
+    ReserveDeposit{asset:ActorId, amount:u128},
+    ReserveWithdraw{asset:ActorId, amount:u128},
+    ReserveLoanTaken{asset:ActorId, amount:u128},
+    ReserveLoanPayed{asset:ActorId, amount:u128},
+    VaraPoolUpdated,
+    VaraPoolDeposit{amount:u128},
+    VaraPoolWithdraw{amount:u128},
+    DepositedStableCollateral{amount:u128},
+    WithdrawnStableCollateral{amount:u128},
+    VaraLoanTaken{amount:u128},
+    VaraLoanPayed{amount:u128},
+    VaraLoanLiquidated{user:ActorId, loan_amount:u128, collateral_seized:u128},
+    TermLoanTaken{loan_id:u64, amount:u128, rate:u128, maturity:u128},
+    TermLoanPayed{loan_id:u64, amount:u128},
+    TermLoanLiquidated{loan_id:u64, user:ActorId, loan_amount:u128, collateral_seized:u128},
+    StableLoanTaken{amount:u128, stable_rate:u128},
+    StableLoanPayed{amount:u128},
+    StableRateRebalanced{user:ActorId, stable_rate:u128},
+    MarketCreated{market_id:u32, vft_contract_id:ActorId},
+    EModeCategoryUpdated{category_id:u8},
+    UserEModeSet{user:ActorId, category_id:u8},
+    CdpUpdated{stablecoin:ActorId, stability_fee:u128, debt_ceiling:u128},
+    VaultLiquidated{user:ActorId, liquidator:ActorId, debt:u128, collateral_seized:u128},
+    SavingsRateUpdated{rate:u128},
+    SavingsDeposit{amount:u128},
+    SavingsWithdraw{amount:u128},
+    GovernanceUpdated{token:ActorId},
+    GovernanceTokensLocked{amount:u128},
+    GovernanceTokensUnlocked{amount:u128},
+    ProposalCreated{proposal_id:u64, proposer:ActorId},
+    VoteCast{proposal_id:u64, voter:ActorId, support:bool, weight:u128},
+    ProposalQueued{proposal_id:u64, eta:u128},
+    ProposalExecuted{proposal_id:u64},
+    ProposalCanceled{proposal_id:u64},
+    MulticallExecuted{actions:u32},
+    PositionClosed{debt_repaid:u128, collateral_returned:u128, rewards_claimed:u128},
+    SwapVenueUpdated{venue:ActorId, max_slippage:u128},
+    Leveraged{iterations:u32, borrowed:u128, collateral_added:u128},
+    Deleveraged{iterations:u32, collateral_sold:u128, debt_repaid:u128},
+    CollateralSwapped{from_asset:ActorId, to_asset:ActorId, amount_in:u128, amount_out:u128},
+    SelfLiquidated{user:ActorId, debt_repaid:u128, collateral_sold:u128, fee:u128},
+    PositionAtRisk{user:ActorId, ltv:u128, threshold:u128},
+    RiskWarningBandUpdated{warning_band:u128},
+    PendingOperationCleared{operation_id:MessageId},
 }
 
 pub struct LiquidityInjectionService<VftClient>{
     pub vft_client: VftClient,
+    // Isolated market this call operates on, `None` for the default market.
+    // Set per message, so it stays put across awaits.
+    market_id: Option<u32>,
+    // Token flows deferred while a multicall runs, settled once every action succeeded
+    settlement: Option<Settlement>,
 }
 
 impl<VftClient> EventNotifier for LiquidityInjectionService<VftClient>
 where
-    VftClient: Vft,
+    VftClient: Vft<Args = GStdArgs>,
 {
     fn notify_deposit(&mut self, amount: u128) {
         self.notify_on(LiquidityEvent::Deposit { amount })
@@ -100,11 +190,281 @@ where
         self.notify_on(LiquidityEvent::LoanPayed { amount })
             .expect("Notification Error");
     }
+
+    fn notify_loan_repaid_for(&mut self, payer: ActorId, borrower: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::LoanRepaidFor { payer, borrower, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_collateral_deposited_for(&mut self, depositor: ActorId, borrower: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::CollateralDepositedFor { depositor, borrower, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_delegation_approved(&mut self, delegator: ActorId, delegatee: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::DelegationApproved { delegator, delegatee, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_loan_taken_on_behalf(&mut self, delegator: ActorId, delegatee: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::LoanTakenOnBehalf { delegator, delegatee, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_caps_updated(&mut self, supply_cap: u128, borrow_cap: u128, collateral_cap: u128) {
+        self.notify_on(LiquidityEvent::CapsUpdated { supply_cap, borrow_cap, collateral_cap })
+            .expect("Notification Error");
+    }
+
+    fn notify_collateral_asset_listed(&mut self, asset: ActorId) {
+        self.notify_on(LiquidityEvent::CollateralAssetListed { asset })
+            .expect("Notification Error");
+    }
+
+    fn notify_collateral_asset_updated(&mut self, asset: ActorId) {
+        self.notify_on(LiquidityEvent::CollateralAssetUpdated { asset })
+            .expect("Notification Error");
+    }
+
+    fn notify_collateral_price_updated(&mut self, asset: ActorId, price: u128) {
+        self.notify_on(LiquidityEvent::CollateralPriceUpdated { asset, price })
+            .expect("Notification Error");
+    }
+
+    fn notify_deposited_token_collateral(&mut self, asset: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::DepositedTokenCollateral { asset, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_withdrawn_token_collateral(&mut self, asset: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::WithdrawnTokenCollateral { asset, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_reserve_updated(&mut self, asset: ActorId) {
+        self.notify_on(LiquidityEvent::ReserveUpdated { asset })
+            .expect("Notification Error");
+    }
+
+    fn notify_reserve_deposit(&mut self, asset: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::ReserveDeposit { asset, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_reserve_withdraw(&mut self, asset: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::ReserveWithdraw { asset, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_reserve_loan_taken(&mut self, asset: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::ReserveLoanTaken { asset, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_reserve_loan_payed(&mut self, asset: ActorId, amount: u128) {
+        self.notify_on(LiquidityEvent::ReserveLoanPayed { asset, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_vara_pool_updated(&mut self) {
+        self.notify_on(LiquidityEvent::VaraPoolUpdated)
+            .expect("Notification Error");
+    }
+
+    fn notify_vara_pool_deposit(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::VaraPoolDeposit { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_vara_pool_withdraw(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::VaraPoolWithdraw { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_deposited_stable_collateral(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::DepositedStableCollateral { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_withdrawn_stable_collateral(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::WithdrawnStableCollateral { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_vara_loan_taken(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::VaraLoanTaken { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_vara_loan_payed(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::VaraLoanPayed { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_vara_loan_liquidated(&mut self, user: ActorId, loan_amount: u128, collateral_seized: u128) {
+        self.notify_on(LiquidityEvent::VaraLoanLiquidated { user, loan_amount, collateral_seized })
+            .expect("Notification Error");
+    }
+
+    fn notify_term_loan_taken(&mut self, loan_id: u64, amount: u128, rate: u128, maturity: u128) {
+        self.notify_on(LiquidityEvent::TermLoanTaken { loan_id, amount, rate, maturity })
+            .expect("Notification Error");
+    }
+
+    fn notify_term_loan_payed(&mut self, loan_id: u64, amount: u128) {
+        self.notify_on(LiquidityEvent::TermLoanPayed { loan_id, amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_term_loan_liquidated(&mut self, loan_id: u64, user: ActorId, loan_amount: u128, collateral_seized: u128) {
+        self.notify_on(LiquidityEvent::TermLoanLiquidated { loan_id, user, loan_amount, collateral_seized })
+            .expect("Notification Error");
+    }
+
+    fn notify_stable_loan_taken(&mut self, amount: u128, stable_rate: u128) {
+        self.notify_on(LiquidityEvent::StableLoanTaken { amount, stable_rate })
+            .expect("Notification Error");
+    }
+
+    fn notify_stable_loan_payed(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::StableLoanPayed { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_stable_rate_rebalanced(&mut self, user: ActorId, stable_rate: u128) {
+        self.notify_on(LiquidityEvent::StableRateRebalanced { user, stable_rate })
+            .expect("Notification Error");
+    }
+
+    fn notify_emode_category_updated(&mut self, category_id: u8) {
+        self.notify_on(LiquidityEvent::EModeCategoryUpdated { category_id })
+            .expect("Notification Error");
+    }
+
+    fn notify_user_emode_set(&mut self, user: ActorId, category_id: u8) {
+        self.notify_on(LiquidityEvent::UserEModeSet { user, category_id })
+            .expect("Notification Error");
+    }
+
+    fn notify_cdp_updated(&mut self, stablecoin: ActorId, stability_fee: u128, debt_ceiling: u128) {
+        self.notify_on(LiquidityEvent::CdpUpdated { stablecoin, stability_fee, debt_ceiling })
+            .expect("Notification Error");
+    }
+
+    fn notify_vault_liquidated(&mut self, user: ActorId, liquidator: ActorId, debt: u128, collateral_seized: u128) {
+        self.notify_on(LiquidityEvent::VaultLiquidated { user, liquidator, debt, collateral_seized })
+            .expect("Notification Error");
+    }
+
+    fn notify_savings_rate_updated(&mut self, rate: u128) {
+        self.notify_on(LiquidityEvent::SavingsRateUpdated { rate })
+            .expect("Notification Error");
+    }
+
+    fn notify_savings_deposit(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::SavingsDeposit { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_savings_withdraw(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::SavingsWithdraw { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_governance_updated(&mut self, token: ActorId) {
+        self.notify_on(LiquidityEvent::GovernanceUpdated { token })
+            .expect("Notification Error");
+    }
+
+    fn notify_governance_tokens_locked(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::GovernanceTokensLocked { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_governance_tokens_unlocked(&mut self, amount: u128) {
+        self.notify_on(LiquidityEvent::GovernanceTokensUnlocked { amount })
+            .expect("Notification Error");
+    }
+
+    fn notify_proposal_created(&mut self, proposal_id: u64, proposer: ActorId) {
+        self.notify_on(LiquidityEvent::ProposalCreated { proposal_id, proposer })
+            .expect("Notification Error");
+    }
+
+    fn notify_vote_cast(&mut self, proposal_id: u64, voter: ActorId, support: bool, weight: u128) {
+        self.notify_on(LiquidityEvent::VoteCast { proposal_id, voter, support, weight })
+            .expect("Notification Error");
+    }
+
+    fn notify_proposal_queued(&mut self, proposal_id: u64, eta: u128) {
+        self.notify_on(LiquidityEvent::ProposalQueued { proposal_id, eta })
+            .expect("Notification Error");
+    }
+
+    fn notify_proposal_executed(&mut self, proposal_id: u64) {
+        self.notify_on(LiquidityEvent::ProposalExecuted { proposal_id })
+            .expect("Notification Error");
+    }
+
+    fn notify_proposal_canceled(&mut self, proposal_id: u64) {
+        self.notify_on(LiquidityEvent::ProposalCanceled { proposal_id })
+            .expect("Notification Error");
+    }
+
+    fn notify_multicall_executed(&mut self, actions: u32) {
+        self.notify_on(LiquidityEvent::MulticallExecuted { actions })
+            .expect("Notification Error");
+    }
+
+    fn notify_position_closed(&mut self, debt_repaid: u128, collateral_returned: u128, rewards_claimed: u128) {
+        self.notify_on(LiquidityEvent::PositionClosed { debt_repaid, collateral_returned, rewards_claimed })
+            .expect("Notification Error");
+    }
+
+    fn notify_swap_venue_updated(&mut self, venue: ActorId, max_slippage: u128) {
+        self.notify_on(LiquidityEvent::SwapVenueUpdated { venue, max_slippage })
+            .expect("Notification Error");
+    }
+
+    fn notify_leveraged(&mut self, iterations: u32, borrowed: u128, collateral_added: u128) {
+        self.notify_on(LiquidityEvent::Leveraged { iterations, borrowed, collateral_added })
+            .expect("Notification Error");
+    }
+
+    fn notify_deleveraged(&mut self, iterations: u32, collateral_sold: u128, debt_repaid: u128) {
+        self.notify_on(LiquidityEvent::Deleveraged { iterations, collateral_sold, debt_repaid })
+            .expect("Notification Error");
+    }
+
+    fn notify_collateral_swapped(&mut self, from_asset: ActorId, to_asset: ActorId, amount_in: u128, amount_out: u128) {
+        self.notify_on(LiquidityEvent::CollateralSwapped { from_asset, to_asset, amount_in, amount_out })
+            .expect("Notification Error");
+    }
+
+    fn notify_self_liquidated(&mut self, user: ActorId, debt_repaid: u128, collateral_sold: u128, fee: u128) {
+        self.notify_on(LiquidityEvent::SelfLiquidated { user, debt_repaid, collateral_sold, fee })
+            .expect("Notification Error");
+    }
+
+    fn notify_position_at_risk(&mut self, user: ActorId, ltv: u128, threshold: u128) {
+        self.notify_on(LiquidityEvent::PositionAtRisk { user, ltv, threshold })
+            .expect("Notification Error");
+    }
+
+    fn notify_risk_warning_band_updated(&mut self, warning_band: u128) {
+        self.notify_on(LiquidityEvent::RiskWarningBandUpdated { warning_band })
+            .expect("Notification Error");
+    }
+
+    fn notify_pending_operation_cleared(&mut self, operation_id: MessageId) {
+        self.notify_on(LiquidityEvent::PendingOperationCleared { operation_id })
+            .expect("Notification Error");
+    }
 }
 
 #[sails_rs::service(events = LiquidityEvent)]
 impl<VftClient> LiquidityInjectionService<VftClient> 
-where VftClient: Vft, {
+where VftClient: Vft<Args = GStdArgs>, {
     // Service's constructor
     pub fn seed(
         owner: ActorId,
@@ -122,23 +482,17 @@ where VftClient: Vft, {
         config: Config,
     ) {
         unsafe {
-            VSTREET_STATE = Some(
-                VstreetState {
-                    owner,
-                    admins,
-                    vft_contract_id,
-                    total_deposited,
-                    total_borrowed,
-                    available_rewards_pool,
-                    total_rewards_distributed,
-                    users,
-                    utilization_factor,
-                    interest_rate,
-                    apr,
-                    ltv,
-                    config,
-                }
-            );
+            VSTREET_STATE = Some(VstreetState {
+                total_deposited,
+                total_borrowed,
+                available_rewards_pool,
+                total_rewards_distributed,
+                users,
+                utilization_factor,
+                interest_rate,
+                apr,
+                ..Self::initial_state(owner, admins, vft_contract_id, ltv, config)
+            });
         };
     }
 
@@ -147,6 +501,8 @@ where VftClient: Vft, {
     ) -> Self {
         Self {
             vft_client,
+            market_id: None,
+            settlement: None,
         }
     }
 
@@ -185,6 +541,23 @@ where VftClient: Vft, {
         }
     }
 
+    // Admins always hold the risk role, risk managers only hold this one
+    fn ensure_risk_manager(&mut self) -> Result<(), String> {
+        let state = self.state_mut();
+        let caller = msg::source();
+
+        if !state.admins.contains(&caller) && !state.risk_managers.contains(&caller) {
+            let error_message = ERROR_INSUFFICIENT_RISK_PRIVILEGES.to_string();
+
+            self.notify_on(LiquidityEvent::Error(error_message.clone()))
+                .expect("Notification Error");
+
+            return Err(error_message);
+        }
+
+        Ok(())
+    }
+
     pub fn add_admin(&mut self, new_admin: ActorId) -> Result<(), String> {
         self.ensure_admin()?;
 
@@ -237,6 +610,138 @@ where VftClient: Vft, {
         Ok(())
     }
 
+    pub fn add_risk_manager(&mut self, risk_manager: ActorId) -> Result<(), String> {
+        self.ensure_admin()?;
+
+        let state = self.state_mut();
+
+        if state.risk_managers.contains(&risk_manager) {
+            let error_message = ERROR_RISK_MANAGER_ALREADY_EXISTS.to_string();
+            self.notify_on(LiquidityEvent::Error(error_message.clone()))
+                .expect("Notification Error");
+            return Err(error_message);
+        }
+
+        state.risk_managers.push(risk_manager);
+
+        Ok(())
+    }
+
+    pub fn remove_risk_manager(&mut self, risk_manager: ActorId) -> Result<(), String> {
+        self.ensure_admin()?;
+
+        let state = self.state_mut();
+
+        if let Some(pos) = state.risk_managers.iter().position(|x| *x == risk_manager) {
+            state.risk_managers.remove(pos);
+        } else {
+            let error_message = ERROR_RISK_MANAGER_DOESNT_EXIST.to_string();
+            self.notify_on(LiquidityEvent::Error(error_message.clone()))
+                .expect("Notification Error");
+            return Err(error_message)
+        }
+
+        Ok(())
+    }
+
+    // Risk methods
+    // Only risk managers (or administrators) can perform this actions.
+
+    // ## Protocol-wide cap on total_deposited (raw token units)
+    pub fn set_supply_cap(&mut self, supply_cap: u128) -> Result<(), String> {
+        self.ensure_risk_manager()?;
+
+        let state = self.state_mut();
+        state.config.supply_cap = supply_cap;
+
+        self.notify_caps_updated(state.config.supply_cap, state.config.borrow_cap, state.config.collateral_cap);
+
+        Ok(())
+    }
+
+    // ## Protocol-wide cap on total_borrowed (raw token units)
+    pub fn set_borrow_cap(&mut self, borrow_cap: u128) -> Result<(), String> {
+        self.ensure_risk_manager()?;
+
+        let state = self.state_mut();
+        state.config.borrow_cap = borrow_cap;
+
+        self.notify_caps_updated(state.config.supply_cap, state.config.borrow_cap, state.config.collateral_cap);
+
+        Ok(())
+    }
+
+    // ## Protocol-wide cap on total VARA collateral (raw VARA units)
+    pub fn set_collateral_cap(&mut self, collateral_cap: u128) -> Result<(), String> {
+        self.ensure_risk_manager()?;
+
+        let state = self.state_mut();
+        state.config.collateral_cap = collateral_cap;
+
+        self.notify_caps_updated(state.config.supply_cap, state.config.borrow_cap, state.config.collateral_cap);
+
+        Ok(())
+    }
+
+    // ## Percentage points below the liquidation threshold at which a position is flagged at risk
+    pub fn set_risk_warning_band(&mut self, warning_band: u128) -> Result<(), String> {
+        self.ensure_risk_manager()?;
+        risk::set_risk_warning_band(self, warning_band)
+    }
+
+    // Move a user's stable rate to the current market rate once they diverge
+    // by more than the rebalance delta. Admins and risk managers act as keepers.
+    pub fn rebalance_stable_rate(&mut self, user: ActorId) -> Result<(), String> {
+        self.ensure_risk_manager()?;
+        stable_rate::rebalance_stable_rate(self, user)
+    }
+
+    // Isolated markets
+    // Default market administrators create markets and become their first admins.
+
+    pub fn create_market(&mut self, market_id: u32, vft_contract_id: ActorId, ltv: u128) -> Result<(), String> {
+        self.ensure_admin()?;
+
+        if isolated_markets().contains_key(&market_id) {
+            let error_message = ERROR_MARKET_ALREADY_EXISTS.to_string();
+            self.notify_on(LiquidityEvent::Error(error_message.clone()))
+                .expect("Notification Error");
+            return Err(error_message);
+        }
+
+        if ltv == 0 || ltv > 95 {
+            let error_message = ERROR_INVALID_LTV.to_string();
+            self.notify_on(LiquidityEvent::Error(error_message.clone()))
+                .expect("Notification Error");
+            return Err(error_message);
+        }
+
+        let owner = msg::source();
+        let market = Self::initial_state(owner, vec![owner], Some(vft_contract_id), ltv, Config::default());
+
+        isolated_markets().insert(market_id, market);
+
+        self.notify_on(LiquidityEvent::MarketCreated { market_id, vft_contract_id })
+            .expect("Notification Error");
+
+        Ok(())
+    }
+
+    pub async fn market_set_vara_price(&mut self, market_id: u32, vara_price: u128) -> Result<String, String> {
+        self.enter_market(market_id)?;
+        Ok(self.set_vara_price(vara_price).await)
+    }
+
+    pub fn market_set_ltv(&mut self, market_id: u32, ltv: u128) -> Result<String, String> {
+        self.enter_market(market_id)?;
+        Ok(self.set_ltv(ltv))
+    }
+
+    pub async fn market_modify_available_rewards_pool(&mut self, market_id: u32, amount: u128) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        self.modify_available_rewards_pool(amount).await
+    }
+
     // Private methods
     // Only administrators of the contract can perform this actions.
 
@@ -291,17 +796,172 @@ where VftClient: Vft, {
     pub async fn set_vara_price(&mut self, vara_price: u128) -> String {
         self.ensure_admin_or_panic();
 
+        if let Err(error_message) = locks::acquire_global(self) {
+            panic!("{}", error_message);
+        }
+
         let state = self.state_mut();
 
         state.config.vara_price = vara_price;
 
         self.update_cv_and_mla_for_all_users();
         self.update_all_ltv().await;
-        let _ = self.liquidate_all_loans();
+        let _ = self.liquidate_all_loans().await;
+
+        // A higher VARA price makes VARA pool loans riskier
+        self.update_all_inverse_positions();
+        let _ = self.liquidate_all_vara_loans().await;
+
+        locks::release_global(self);
 
         format!("New Vara price set: {:?}", vara_price)
     }
 
+    // Collateral registry
+    // Only administrators can list or reconfigure assets; prices come from each asset's feed.
+
+    pub fn add_collateral_asset(
+        &mut self,
+        asset: ActorId,
+        price_feed: ActorId,
+        one_token: u128,
+        collateral_factor: u128,
+        liquidation_threshold: u128,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        collateral::add_collateral_asset(self, asset, price_feed, one_token, collateral_factor, liquidation_threshold)
+    }
+
+    pub fn update_collateral_asset(
+        &mut self,
+        asset: ActorId,
+        price_feed: ActorId,
+        collateral_factor: u128,
+        liquidation_threshold: u128,
+        enabled: bool,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        collateral::update_collateral_asset(self, asset, price_feed, collateral_factor, liquidation_threshold, enabled)
+    }
+
+    pub fn set_cdp(&mut self, stablecoin: ActorId, stability_fee: u128, debt_ceiling: u128) -> Result<(), String> {
+        self.ensure_admin()?;
+        cdp::set_cdp(self, stablecoin, stability_fee, debt_ceiling)
+    }
+
+    pub fn set_swap_venue(&mut self, venue: ActorId, max_slippage: u128) -> Result<(), String> {
+        self.ensure_admin()?;
+        dex::set_swap_venue(self, venue, max_slippage)
+    }
+
+    pub fn clear_pending_operation(&mut self, operation_id: MessageId) -> Result<(), String> {
+        self.ensure_admin()?;
+        recovery::clear_pending_operation(self, operation_id)
+    }
+
+    pub fn set_governance(
+        &mut self,
+        token: ActorId,
+        voting_period: u128,
+        timelock_delay: u128,
+        proposal_threshold: u128,
+        quorum: u128,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        governance::set_governance(self, token, voting_period, timelock_delay, proposal_threshold, quorum)
+    }
+
+    // Config changes queued here wait out the same timelock as passed proposals
+    pub fn queue_config_change(&mut self, change: ConfigChange) -> Result<u64, String> {
+        self.ensure_admin()?;
+        governance::queue_config_change(self, change)
+    }
+
+    pub fn cancel_proposal(&mut self, proposal_id: u64) -> Result<(), String> {
+        self.ensure_admin()?;
+        governance::cancel_proposal(self, proposal_id)
+    }
+
+    pub fn set_savings_rate(&mut self, rate: u128) -> Result<(), String> {
+        self.ensure_admin()?;
+        savings::set_savings_rate(self, rate)
+    }
+
+    pub fn set_isolation(&mut self, asset: ActorId, debt_ceiling: u128, borrowable: Vec<ActorId>) -> Result<(), String> {
+        self.ensure_admin()?;
+        isolation::set_isolation(self, asset, debt_ceiling, borrowable)
+    }
+
+    pub fn set_emode_category(
+        &mut self,
+        category_id: u8,
+        ltv: u128,
+        liquidation_threshold: u128,
+        assets: Vec<ActorId>,
+        label: String,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        emode::set_emode_category(self, category_id, ltv, liquidation_threshold, assets, label)
+    }
+
+    pub async fn set_collateral_price(&mut self, asset: ActorId, price: u128) -> Result<(), String> {
+        locks::acquire_global(self)?;
+
+        if let Err(error_message) = collateral::set_collateral_price(self, asset, price) {
+            locks::release_global(self);
+            return Err(error_message);
+        }
+
+        self.update_cv_and_mla_for_all_users();
+        self.update_all_ltv().await;
+        let _ = self.liquidate_all_loans().await;
+
+        locks::release_global(self);
+
+        Ok(())
+    }
+
+    // Reserves
+    // Only administrators can list or reconfigure reserves.
+
+    pub fn add_reserve(
+        &mut self,
+        asset: ActorId,
+        decimals_factor: u128,
+        params: ReserveParams,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        reserves::add_reserve(self, asset, decimals_factor, params)
+    }
+
+    pub fn update_reserve(
+        &mut self,
+        asset: ActorId,
+        params: ReserveParams,
+        enabled: bool,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        reserves::update_reserve(self, asset, params, enabled)
+    }
+
+    // VARA pool
+    // Only administrators can reconfigure the VARA pool.
+
+    pub fn update_vara_pool(
+        &mut self,
+        base_rate: u128,
+        risk_multiplier: u128,
+        dev_fee: u128,
+        supply_cap: u128,
+        borrow_cap: u128,
+        enabled: bool,
+    ) -> Result<(), String> {
+        self.ensure_admin()?;
+        vara_pool::update_vara_pool(self, base_rate, risk_multiplier, dev_fee, supply_cap, borrow_cap, enabled)?;
+        self.notify_vara_pool_updated();
+        Ok(())
+    }
+
     // Queries
 
     // Service's query owner of the contract
@@ -346,6 +1006,262 @@ where VftClient: Vft, {
         }
     }
 
+    //Service's query remaining delegated borrowing allowance
+    pub fn delegation_allowance(&self, delegator: ActorId, delegatee: ActorId) -> String {
+        let state = self.state_ref();
+        state
+            .delegations
+            .get(&(delegator, delegatee))
+            .copied()
+            .unwrap_or(0)
+            .to_string()
+    }
+
+    //Service's query listed collateral assets
+    pub fn collateral_assets(&self) -> String {
+        let state = self.state_ref();
+        format!("Collateral Assets: {:?}", state.collateral_assets)
+    }
+
+    //Service's query CDP mode parameters and outstanding vault debt
+    pub fn cdp_info(&self) -> String {
+        let state = self.state_ref();
+        match state.cdp.as_ref() {
+            Some(cdp) => format!(
+                "Stablecoin: {:?}, Stability Fee: {:?}, Debt Ceiling: {:?}, Total Debt: {:?}, Accrued Fees: {:?}",
+                state.vft_contract_id,
+                cdp.stability_fee,
+                cdp.debt_ceiling,
+                state.total_borrowed,
+                cdp.accrued_fees
+            ),
+            None => "CDP mode disabled".to_string(),
+        }
+    }
+
+    //Service's query savings balance of a user, accrued yield included
+    pub fn savings_balance(&self, user: ActorId) -> String {
+        let state = self.state_ref();
+        savings::balance_of(state, &user).to_string()
+    }
+
+    //Service's query swap venue used by leverage and deleverage
+    pub fn swap_venue_info(&self) -> String {
+        match self.state_ref().swap_venue.as_ref() {
+            Some(venue) => format!(
+                "Venue: {:?}, Max Slippage: {:?}",
+                venue.program,
+                venue.max_slippage
+            ),
+            None => "Swap venue not configured".to_string(),
+        }
+    }
+
+    //Service's query savings rate and totals
+    pub fn savings_info(&self) -> String {
+        let state = self.state_ref();
+        format!(
+            "Savings Rate: {:?}, Chi: {:?}, Total Normalized: {:?}",
+            state.savings.rate,
+            state.savings.chi,
+            state.savings.total_normalized
+        )
+    }
+
+    //Service's query governance parameters
+    pub fn governance_info(&self) -> String {
+        let state = self.state_ref();
+        let governance = &state.governance;
+        format!(
+            "Token: {:?}, Voting Period: {:?}, Timelock Delay: {:?}, Proposal Threshold: {:?}, Quorum: {:?}, Total Locked: {:?}",
+            governance.token,
+            governance.voting_period,
+            governance.timelock_delay,
+            governance.proposal_threshold,
+            governance.quorum,
+            governance.total_locked
+        )
+    }
+
+    //Service's query a governance proposal
+    pub fn proposal(&self, proposal_id: u64) -> String {
+        let state = self.state_ref();
+        match state.governance.proposals.get(&proposal_id) {
+            Some(proposal) => format!("{:?}", proposal),
+            None => "Proposal not found".to_string(),
+        }
+    }
+
+    //Service's query governance tokens currently locked by a holder
+    pub fn governance_balance(&self, holder: ActorId) -> String {
+        let state = self.state_ref();
+        governance::current_locked(&state.governance, &holder).to_string()
+    }
+
+    //Service's query isolation debt ceiling and debt currently backed by an isolated collateral
+    pub fn isolation_info(&self, asset: ActorId) -> String {
+        let state = self.state_ref();
+        match state.collateral_assets.get(&asset) {
+            Some(collateral_asset) => format!(
+                "Debt Ceiling: {:?}, Isolated Debt: {:?}, Borrowable: {:?}",
+                collateral_asset.debt_ceiling,
+                isolation::isolated_debt(state, &asset),
+                collateral_asset.isolation_borrowable
+            ),
+            None => "Collateral asset not found".to_string(),
+        }
+    }
+
+    //Service's query e-mode categories
+    pub fn emode_categories(&self) -> String {
+        let state = self.state_ref();
+        format!("E-Mode Categories: {:?}", state.emode_categories)
+    }
+
+    //Service's query reserves (without per-user positions)
+    pub fn reserves_info(&self) -> String {
+        let state = self.state_ref();
+        let reserves = state
+            .reserves
+            .iter()
+            .map(|(asset, reserve)| format!(
+                "Reserve: {:?}, APR: {:?}, Interest Rate: {:?}, Total Deposited: {:?}, Total Borrowed: {:?}, Utilization Factor: {:?}, Supply Cap: {:?}, Borrow Cap: {:?}, Enabled: {:?}",
+                asset, reserve.apr, reserve.interest_rate, reserve.total_deposited, reserve.total_borrowed,
+                reserve.utilization_factor, reserve.supply_cap, reserve.borrow_cap, reserve.enabled
+            ))
+            .collect::<Vec<_>>();
+        reserves.join("; ")
+    }
+
+    //Service's query user position in a reserve
+    pub fn reserve_position(&self, asset: ActorId, user: ActorId) -> String {
+        let state = self.state_ref();
+        match state.reserves.get(&asset) {
+            Some(reserve) => match reserve.positions.get(&user) {
+                Some(position) => format!("Reserve Position: {:?}", position),
+                None => "User not found".to_string(),
+            },
+            None => "Reserve not found".to_string(),
+        }
+    }
+
+    //Service's query term loans of a user
+    pub fn user_term_loans(&self, user: ActorId) -> String {
+        let term_loans = self
+            .state_ref()
+            .term_loans
+            .iter()
+            .filter(|(_, term_loan)| term_loan.borrower == user)
+            .map(|(loan_id, term_loan)| format!("Loan Id: {:?}, {:?}", loan_id, term_loan))
+            .collect::<Vec<_>>();
+        term_loans.join("; ")
+    }
+
+    //Service's query VARA pool (without per-user positions)
+    pub fn vara_pool_info(&self) -> String {
+        let vara_pool = &self.state_ref().vara_pool;
+        format!(
+            "APR: {:?}, Interest Rate: {:?}, Total Deposited: {:?}, Total Borrowed: {:?}, Utilization Factor: {:?}, Supply Cap: {:?}, Borrow Cap: {:?}, Enabled: {:?}",
+            vara_pool.apr, vara_pool.interest_rate, vara_pool.total_deposited, vara_pool.total_borrowed,
+            vara_pool.utilization_factor, vara_pool.supply_cap, vara_pool.borrow_cap, vara_pool.enabled
+        )
+    }
+
+    //Service's query VARA pool position
+    pub fn vara_pool_position(&self, user: ActorId) -> String {
+        match self.state_ref().vara_pool.positions.get(&user) {
+            Some(position) => format!("VARA Pool Position: {:?}", position),
+            None => "Position not found".to_string(),
+        }
+    }
+
+    //Service's query positions flagged at risk, `limit` of them after `cursor`
+    pub fn positions_at_risk(&self, limit: u32, cursor: Option<ActorId>) -> String {
+        let positions = risk::positions_at_risk(self.state_ref(), limit, cursor)
+            .iter()
+            .map(|(user, ltv, threshold)| {
+                format!("User: {:?}, LTV: {:?}, Threshold: {:?}", user, ltv, threshold)
+            })
+            .collect::<Vec<_>>();
+
+        positions.join("; ")
+    }
+
+    //Service's query operations waiting on replies or left behind by a trapped message
+    pub fn pending_operations(&self) -> String {
+        let operations = recovery::pending_operations(self.state_ref())
+            .iter()
+            .map(|(operation_id, operation)| {
+                format!(
+                    "Operation: {:?}, Users: {:?}, Started At: {:?}, Status: {:?}, Sent Transfers: {:?}, Confirmed Transfers: {:?}, Failed Transfers: {:?}",
+                    operation_id,
+                    operation.users,
+                    operation.started_at,
+                    operation.status,
+                    operation.sent_transfers,
+                    operation.confirmed_transfers,
+                    operation.failed_transfers,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        operations.join("; ")
+    }
+
+    //Service's query CV, MLA and LTV of the user with interest accrued up to now
+    pub fn user_position(&self, user: ActorId) -> String {
+        match preview::position(self, user) {
+            Some(position) => format!("Position: {:?}", position),
+            None => "User not found".to_string(),
+        }
+    }
+
+    //Service's query interest accrued on the user's loan since it was last applied
+    pub fn pending_interest(&self, user: ActorId) -> String {
+        match preview::pending_interest(self, user) {
+            Some(interest) => format!("Loan Interest Rate Amount: {:?}", interest),
+            None => "User not found".to_string(),
+        }
+    }
+
+    //Service's query utilization factor, lender APR and borrower rate from the current totals
+    pub fn pool_rates(&self) -> String {
+        let rates = preview::preview_deposit(self, 0);
+        format!(
+            "Utilization Factor: {:?}, APR: {:?}, Interest Rate: {:?}",
+            rates.utilization_factor, rates.apr, rates.interest_rate
+        )
+    }
+
+    //Service's query position after borrowing `amount` more
+    pub fn preview_take_loan(&self, user: ActorId, amount: u128) -> String {
+        match preview::preview_take_loan(self, user, amount) {
+            Some(position) => format!("Position Preview: {:?}", position),
+            None => "User not found".to_string(),
+        }
+    }
+
+    //Service's query position after withdrawing `amount` TVARA of collateral
+    pub fn preview_withdraw_collateral(&self, user: ActorId, amount: u128) -> String {
+        match preview::preview_withdraw_collateral(self, user, amount) {
+            Some(position) => format!("Position Preview: {:?}", position),
+            None => "User not found".to_string(),
+        }
+    }
+
+    //Service's query VARA price at which the user's loan gets liquidated
+    pub fn liquidation_price(&self, user: ActorId) -> String {
+        match preview::liquidation_price(self, user) {
+            Some(price) => format!("Liquidation Price: {:?}", price),
+            None => "User not found".to_string(),
+        }
+    }
+
+    //Service's query pool rates after depositing `amount` of liquidity
+    pub fn preview_deposit(&self, amount: u128) -> String {
+        format!("Deposit Preview: {:?}", preview::preview_deposit(self, amount))
+    }
+
     //Service's query all users
     pub fn all_users(&self) -> String {
         let state = self.state_ref();
@@ -369,14 +1285,64 @@ where VftClient: Vft, {
         state.total_deposited.to_string()
     }
 
+    //Service's query caps and remaining headroom under each of them
+    pub fn caps_headroom(&self) -> String {
+        let state = self.state_ref();
+        format!(
+            "Supply Cap: {:?}, Supply Headroom: {:?}, Borrow Cap: {:?}, Borrow Headroom: {:?}, Collateral Cap: {:?}, Collateral Headroom: {:?}",
+            state.config.supply_cap, state.config.supply_cap.saturating_sub(state.total_deposited),
+            state.config.borrow_cap, state.config.borrow_cap.saturating_sub(state.total_borrowed),
+            state.config.collateral_cap, state.config.collateral_cap.saturating_sub(state.total_collateral_vara)
+        )
+    }
+
     // State mutable & ref functions
-    pub fn state_mut(&self) -> &'static mut VstreetState {
-        let state = unsafe { VSTREET_STATE.as_mut() };
-        debug_assert!(state.is_some(), "state is not started!");
-        unsafe { state.unwrap_unchecked() }
+    //Service's query isolated markets
+    pub fn markets(&self) -> String {
+        let markets = isolated_markets().iter()
+            .map(|(market_id, market)| {
+                format!("Market: {:?}, VFT: {:?}, LTV: {:?}", market_id, market.vft_contract_id, market.ltv)
+            })
+            .collect::<Vec<_>>();
+        markets.join("; ")
+    }
+
+    //Service's query isolated market info
+    pub fn market_info(&self, market_id: u32) -> String {
+        match isolated_markets().get(&market_id) {
+            Some(state) => format!(
+                "APR: {:?}, Interest Rate: {:?}, Total Deposited: {:?}, Total Borrowed: {:?}, Total Collateral Vara: {:?}, Utilization Factor: {:?}, LTV: {:?}",
+                state.apr, state.interest_rate, state.total_deposited, state.total_borrowed,
+                state.total_collateral_vara, state.utilization_factor, state.ltv
+            ),
+            None => ERROR_MARKET_NOT_FOUND.to_string(),
+        }
+    }
+
+    //Service's query user info in an isolated market
+    pub fn market_user_info(&self, market_id: u32, user: ActorId) -> String {
+        match isolated_markets().get(&market_id) {
+            Some(state) => match state.users.get(&user) {
+                Some(user_info) => format!("User Info: {:?}", user_info),
+                None => "User not found".to_string(),
+            },
+            None => ERROR_MARKET_NOT_FOUND.to_string(),
+        }
     }
 
-    fn state_ref(&self) -> &'static VstreetState {
+    pub(crate) fn state_mut(&self) -> &'static mut VstreetState {
+        market_state(self.market_id)
+    }
+
+    pub(crate) fn market_id(&self) -> Option<u32> {
+        self.market_id
+    }
+
+    pub(crate) fn state_ref(&self) -> &'static VstreetState {
+        if let Some(market_id) = self.market_id {
+            return isolated_markets().get(&market_id).expect(ERROR_MARKET_NOT_FOUND);
+        }
+
         let state = unsafe { VSTREET_STATE.as_ref() };
         debug_assert!(state.is_some(), "state is not started!");
         unsafe { state.unwrap_unchecked() }
@@ -384,6 +1350,97 @@ where VftClient: Vft, {
 
     // Internal methods
 
+    // Fresh state for the default market or an isolated one
+    fn initial_state(
+        owner: ActorId,
+        admins: Vec<ActorId>,
+        vft_contract_id: Option<ActorId>,
+        ltv: u128,
+        config: Config,
+    ) -> VstreetState {
+        VstreetState {
+            owner,
+            admins,
+            risk_managers: Vec::new(),
+            vft_contract_id,
+            total_deposited: 0,
+            total_borrowed: 0,
+            total_collateral_vara: 0,
+            available_rewards_pool: 0,
+            total_rewards_distributed: 0,
+            users: BTreeMap::new(),
+            collateral_assets: BTreeMap::new(),
+            emode_categories: BTreeMap::new(),
+            reserves: BTreeMap::new(),
+            vara_pool: Reserve {
+                decimals_factor: config.one_tvara,
+                total_deposited: 0,
+                total_borrowed: 0,
+                accrued_fees: 0,
+                utilization_factor: 0,
+                apr: config.base_rate,
+                interest_rate: config.base_rate.saturating_add(config.dev_fee),
+                base_rate: config.base_rate,
+                risk_multiplier: config.risk_multiplier,
+                dev_fee: config.dev_fee,
+                supply_cap: u128::MAX,
+                borrow_cap: u128::MAX,
+                enabled: true,
+                last_updated: exec::block_timestamp() as u128,
+                positions: BTreeMap::new(),
+            },
+            total_stable_collateral: 0,
+            term_loans: BTreeMap::new(),
+            next_term_loan_id: 0,
+            cdp: None,
+            savings: Savings {
+                rate: 0,
+                chi: savings::CHI_PRECISION,
+                last_updated: exec::block_timestamp() as u128,
+                total_normalized: 0,
+                balances: BTreeMap::new(),
+            },
+            governance: Governance {
+                token: None,
+                voting_period: 259_200_000,
+                timelock_delay: 172_800_000,
+                proposal_threshold: 0,
+                quorum: 0,
+                total_locked: 0,
+                checkpoints: BTreeMap::new(),
+                proposals: BTreeMap::new(),
+                next_proposal_id: 0,
+            },
+            swap_venue: None,
+            risk_watch: RiskWatch {
+                warning_band: 5,
+                at_risk: BTreeMap::new(),
+            },
+            locks: OperationLocks::default(),
+            pending_operations: BTreeMap::new(),
+            delegations: BTreeMap::new(),
+            utilization_factor: 0,
+            interest_rate: 0,
+            apr: 0,
+            ltv,
+            config,
+        }
+    }
+
+    // Point this call at an isolated market
+    fn enter_market(&mut self, market_id: u32) -> Result<(), String> {
+        if !isolated_markets().contains_key(&market_id) {
+            let error_message = ERROR_MARKET_NOT_FOUND.to_string();
+            self.notify_on(LiquidityEvent::Error(error_message.clone()))
+                .expect("Notification Error");
+            return Err(error_message);
+        }
+
+        self.market_id = Some(market_id);
+
+        Ok(())
+    }
+
     // Create new user
     pub fn create_new_user(timestamp: u128) -> UserInfo {
         UserInfo {
@@ -403,22 +1460,79 @@ where VftClient: Vft, {
             loan_amount_usdc: 0,
             is_loan_active: false,
             ltv: 0,
+            liquidation_threshold: 0,
+            collateral_balances: BTreeMap::new(),
+            stable_collateral: 0,
+            stable_cv: 0,
+            vara_mla: 0,
+            vara_ltv: 0,
+            stable_loan_amount: 0,
+            stable_rate: 0,
+            stable_borrow_last_updated: timestamp,
+            emode_category: 0,
         }
     }
 
     //Transfer tokens
-    pub async fn transfer_tokens(&mut self, from: ActorId, to: ActorId, amount: u128) -> Result<(), String> {
+    pub(crate) async fn transfer_tokens(&mut self, from: ActorId, to: ActorId, amount: u128) -> Result<(), String> {
+        if self.defer_primary(from, amount) {
+            return Ok(());
+        }
+
         let state = self.state_ref();
 
         let contract_id = state.vft_contract_id.ok_or_else(|| {
             "VFT contract ID not configured".to_string()
         })?;
 
+        self.transfer_asset_tokens(contract_id, from, to, amount).await
+    }
+
+    // Record a primary VFT flow for the running multicall instead of moving tokens.
+    // Returns false outside a multicall.
+    pub(crate) fn defer_primary(&mut self, from: ActorId, amount: u128) -> bool {
+        match self.settlement.as_mut() {
+            Some(settlement) => {
+                if from == exec::program_id() {
+                    settlement.outbound = settlement.outbound.saturating_add(amount);
+                } else {
+                    settlement.inbound = settlement.inbound.saturating_add(amount);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Record VARA owed to the caller for the running multicall. Returns false outside a multicall.
+    pub(crate) fn defer_vara(&mut self, amount: u128) -> bool {
+        match self.settlement.as_mut() {
+            Some(settlement) => {
+                settlement.vara_out = settlement.vara_out.saturating_add(amount);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn begin_settlement(&mut self) {
+        self.settlement = Some(Settlement::default());
+    }
+
+    pub(crate) fn take_settlement(&mut self) -> Settlement {
+        self.settlement.take().unwrap_or_default()
+    }
+
+    //Transfer tokens of any VFT asset (collateral assets included)
+    pub(crate) async fn transfer_asset_tokens(&mut self, contract_id: ActorId, from: ActorId, to: ActorId, amount: u128) -> Result<(), String> {
+        // Sent with a reply deposit and timeout so a stuck transfer can be recovered
+        let args = recovery::transfer_args(self, from, to);
         let response = self
-        .vft_client
-        .transfer_from(from, to, U256::from(amount))
-        .send_recv(contract_id)
-        .await;
+            .vft_client
+            .transfer_from(from, to, U256::from(amount))
+            .with_args(args)
+            .send_recv(contract_id)
+            .await;
 
         let Ok(transfer_status) = response else {
             self.notify_on(LiquidityEvent::Error("Error in VFT Contract".to_string()))
@@ -436,23 +1550,24 @@ where VftClient: Vft, {
     }
 
     // Lender APR = base_rate + ((utilization_factor × risk_multiplier) / decimals_factor)
-    pub fn calculate_apr(&mut self) -> u128 {
+    pub(crate) fn calculate_apr(&mut self) -> u128 {
         self.calculate_utilization_factor();
         let state_mut = self.state_mut();
-        let variable_rate = state_mut
-            .utilization_factor
-            .saturating_mul(state_mut.config.risk_multiplier)
-            .checked_div(state_mut.config.decimals_factor)
-            .unwrap_or(0);
-
-        let apr = state_mut
-            .config
-            .base_rate
-            .saturating_add(variable_rate);
+        let apr = Self::apr_for(&state_mut.config, state_mut.utilization_factor);
         state_mut.apr = apr;
         apr
     }
 
+    // Lender APR = base rate + utilization factor * risk multiplier
+    pub(crate) fn apr_for(config: &Config, utilization_factor: u128) -> u128 {
+        let variable_rate = utilization_factor
+            .saturating_mul(config.risk_multiplier)
+            .checked_div(config.decimals_factor)
+            .unwrap_or(0);
+
+        config.base_rate.saturating_add(variable_rate)
+    }
+
     // Update User Rewards
     pub fn update_user_rewards(user_info: &mut UserInfo, interest_rate: u128, decimals_factor: u128, year_in_seconds: u128) {
         let current_timestamp = exec::block_timestamp() as u128;
@@ -484,7 +1599,7 @@ where VftClient: Vft, {
     }
 
     // Update All User Rewards
-    pub fn update_all_rewards(&mut self) {
+    pub(crate) fn update_all_rewards(&mut self) {
         let state_mut = self.state_mut();
 
         for user_info in state_mut.users.values_mut() {
@@ -495,7 +1610,7 @@ where VftClient: Vft, {
     }
 
     // Update all user's collateral available to withdraw.
-    pub fn update_all_collateral_available_to_withdraw(&mut self) {
+    pub(crate) fn update_all_collateral_available_to_withdraw(&mut self) {
         let state_mut = self.state_mut();
 
         for user_info in state_mut.users.values_mut() {
@@ -518,60 +1633,143 @@ where VftClient: Vft, {
     
     // Calculate Collateral Value
     // This functions need to be running every time vara price changes or user balance vara changes
-    pub fn calculate_cv(&mut self, user: ActorId) -> String {
+    pub(crate) fn calculate_cv(&mut self, user: ActorId) -> String {
         let state_mut = self.state_mut();
-        let user_info = match state_mut.users.get_mut(&user) {
-            Some(u) => u,
+        let (cv, weighted_threshold) = match state_mut.users.get(&user) {
+            Some(u) => Self::collateral_value(state_mut, u),
             None => return "User not found".to_string(),
         };
-        let vara_price = state_mut.config.vara_price;
-        let one_tvara = state_mut.config.one_tvara;
 
-        // Multiply before dividing to preserve sub-TVara precision
-        let cv = user_info.balance_vara
-            .saturating_mul(vara_price)
-            .checked_div(one_tvara)
-            .unwrap_or(0);
+        let user_info = state_mut.users.get_mut(&user).unwrap();
         user_info.cv = cv;
+        user_info.liquidation_threshold = weighted_threshold
+            .checked_div(cv)
+            .unwrap_or(state_mut.ltv);
 
         format!("CV: {:?}", cv)
     }
 
+    // CV of a position and the sum of every collateral's value times its liquidation
+    // threshold. Dividing the second by the first gives the weighted threshold.
+    pub(crate) fn collateral_value(state: &VstreetState, user_info: &UserInfo) -> (u128, u128) {
+        // Multiply before dividing to preserve sub-TVara precision
+        let vara_cv = user_info.balance_vara
+            .saturating_mul(state.config.vara_price)
+            .checked_div(state.config.one_tvara)
+            .unwrap_or(0);
+
+        let mut cv = vara_cv;
+        let mut weighted_threshold = vara_cv.saturating_mul(state.ltv);
+
+        for (asset, balance) in user_info.collateral_balances.iter() {
+            if let Some(collateral_asset) = state.collateral_assets.get(asset) {
+                let value = Self::collateral_asset_value(*balance, collateral_asset);
+                let (_, liquidation_threshold) = emode::effective_collateral_params(
+                    &state.emode_categories,
+                    user_info.emode_category,
+                    asset,
+                    collateral_asset,
+                );
+                cv = cv.saturating_add(value);
+                weighted_threshold = weighted_threshold
+                    .saturating_add(value.saturating_mul(liquidation_threshold));
+            }
+        }
+
+        (cv, weighted_threshold)
+    }
+
+    // Value of a token collateral balance, same scale as the CV
+    pub(crate) fn collateral_asset_value(balance: u128, collateral_asset: &CollateralAsset) -> u128 {
+        balance
+            .saturating_mul(collateral_asset.price)
+            .checked_div(collateral_asset.one_token)
+            .unwrap_or(0)
+    }
+
+    // Borrowing power = VARA value * protocol LTV + sum of token values * their collateral factor.
+    // Inside an e-mode category its LTV replaces the factor of the category's assets.
+    pub(crate) fn max_loan_amount(state: &VstreetState, user_info: &UserInfo) -> u128 {
+        let vara_cv = user_info.balance_vara
+            .saturating_mul(state.config.vara_price)
+            .checked_div(state.config.one_tvara)
+            .unwrap_or(0);
+
+        let mut max_loan = vara_cv.saturating_mul(state.ltv) / 100;
+
+        for (asset, balance) in user_info.collateral_balances.iter() {
+            if let Some(collateral_asset) = state.collateral_assets.get(asset) {
+                let value = Self::collateral_asset_value(*balance, collateral_asset);
+                let (collateral_factor, _) = emode::effective_collateral_params(
+                    &state.emode_categories,
+                    user_info.emode_category,
+                    asset,
+                    collateral_asset,
+                );
+                max_loan = max_loan
+                    .saturating_add(value.saturating_mul(collateral_factor) / 100);
+            }
+        }
+
+        max_loan
+    }
+
     // Calculate Maximum Loan Amount
-    pub fn calculate_mla(&mut self, user: ActorId) -> String {
+    pub(crate) fn calculate_mla(&mut self, user: ActorId) -> String {
         let state_mut = self.state_mut();
-        let user_info = match state_mut.users.get_mut(&user) {
-            Some(u) => u,
+        let max_loan = match state_mut.users.get(&user) {
+            Some(u) => Self::max_loan_amount(state_mut, u),
             None => return "User not found".to_string(),
         };
+        // Reserve and term loans draw from the same borrowing power
+        let secondary_debt = term_loans::secondary_debt_value(state_mut, &user);
+        let user_info = state_mut.users.get(&user).unwrap();
+        let outstanding = user_info.loan_amount.saturating_add(secondary_debt);
 
-        let max_loan = (user_info.cv * state_mut.ltv) / 100;
-
-        let remaining = if max_loan > user_info.loan_amount {
-            max_loan - user_info.loan_amount
+        let mut remaining = if max_loan > outstanding {
+            max_loan - outstanding
         } else {
             0
         };
 
+        // Isolated collateral also caps borrowing at its remaining global debt ceiling
+        if let Some(ceiling_left) = isolation::remaining_ceiling(state_mut, &user) {
+            remaining = remaining.min(ceiling_left);
+        }
+
+        let user_info = state_mut.users.get_mut(&user).unwrap();
         user_info.mla = remaining;
 
         format!("MLA: {:?}", remaining)
     }
 
-    fn update_cv_and_mla_for_all_users(&mut self) {
+    pub(crate) fn update_cv_and_mla_for_all_users(&mut self) {
         let state_mut = self.state_mut();
 
         for user in state_mut.users.keys().cloned().collect::<Vec<_>>() {
             let user_info = state_mut.users.get(&user).unwrap();
-            if user_info.balance_vara >= state_mut.config.one_tvara {
+            if user_info.balance_vara >= state_mut.config.one_tvara || !user_info.collateral_balances.is_empty() {
+                self.calculate_cv(user);
+                self.calculate_mla(user);
+            }
+        }
+    }
+
+    pub(crate) fn refresh_emode_users(&mut self, category_id: u8) {
+        let state_mut = self.state_mut();
+
+        for user in state_mut.users.keys().cloned().collect::<Vec<_>>() {
+            if state_mut.users.get(&user).unwrap().emode_category == category_id {
                 self.calculate_cv(user);
                 self.calculate_mla(user);
+                self.update_user_ltv(user);
             }
         }
     }
 
-    pub fn update_user_ltv(&mut self, user: ActorId) -> String {
+    pub(crate) fn update_user_ltv(&mut self, user: ActorId) -> String {
         let state_mut = self.state_mut();
+        let secondary_debt = term_loans::secondary_debt_value(state_mut, &user);
         let user_info = match state_mut.users.get_mut(&user) {
             Some(u) => u,
             None => return "User not found".to_string(),
@@ -581,10 +1779,57 @@ where VftClient: Vft, {
         if user_info.cv == 0 {
             user_info.ltv = 0;
         } else {
-            user_info.ltv = (user_info.loan_amount * 100) / user_info.cv;
+            user_info.ltv = (user_info.loan_amount.saturating_add(secondary_debt) * 100) / user_info.cv;
         }
 
-        format!("LTV: {:?}", user_info.ltv)
+        let ltv = user_info.ltv;
+        risk::track(self, user);
+
+        format!("LTV: {:?}", ltv)
+    }
+
+    // Value stable collateral in VARA, the inverse of the VARA price
+    pub(crate) fn inverse_collateral_value(stable_amount: u128, vara_price: u128, one_tvara: u128) -> u128 {
+        stable_amount
+            .saturating_mul(one_tvara)
+            .checked_div(vara_price)
+            .unwrap_or(0)
+    }
+
+    // CV, MLA and LTV of the VARA pool position, all in VARA
+    pub(crate) fn calculate_inverse_position(&mut self, user: ActorId) {
+        let state_mut = self.state_mut();
+        let loan_amount = state_mut
+            .vara_pool
+            .positions
+            .get(&user)
+            .map(|position| position.loan_amount)
+            .unwrap_or(0);
+        let user_info = match state_mut.users.get_mut(&user) {
+            Some(u) => u,
+            None => return,
+        };
+
+        user_info.stable_cv = Self::inverse_collateral_value(
+            user_info.stable_collateral,
+            state_mut.config.vara_price,
+            state_mut.config.one_tvara,
+        );
+
+        let max_loan = user_info.stable_cv.saturating_mul(state_mut.ltv) / 100;
+        user_info.vara_mla = max_loan.saturating_sub(loan_amount);
+
+        user_info.vara_ltv = (loan_amount * 100).checked_div(user_info.stable_cv).unwrap_or(0);
+    }
+
+    fn update_all_inverse_positions(&mut self) {
+        let state_mut = self.state_mut();
+
+        for user in state_mut.users.keys().cloned().collect::<Vec<_>>() {
+            if state_mut.users.get(&user).unwrap().stable_collateral > 0 {
+                self.calculate_inverse_position(user);
+            }
+        }
     }
 
     //Update all user's ltv
@@ -600,23 +1845,33 @@ where VftClient: Vft, {
     }
 
     // Calculate utilization factor = (Total borrowed / Total deposited) * 100
-    pub fn calculate_utilization_factor(&mut self) -> u128 {
+    pub(crate) fn calculate_utilization_factor(&mut self) -> u128 {
         let state_mut = self.state_mut();
 
-        let total_deposited = state_mut.total_deposited;
-        let total_borrowed = state_mut.total_borrowed;
-        let decimals_factor = state_mut.config.decimals_factor;
+        let utilization_factor = Self::utilization_factor_for(
+            state_mut.total_deposited,
+            state_mut.total_borrowed,
+            state_mut.config.decimals_factor,
+        );
 
-        // Check if total_deposited or total_borrowed is zero
-        if total_deposited == 0 || total_borrowed == 0 {
+        // Nothing is stored while the pool is empty
+        if utilization_factor == 0 {
             return 0;
         }
 
-        let utilization_factor = (total_borrowed * decimals_factor * 100) / total_deposited;
         state_mut.utilization_factor = utilization_factor;
         return utilization_factor;
     }
 
+    pub(crate) fn utilization_factor_for(total_deposited: u128, total_borrowed: u128, decimals_factor: u128) -> u128 {
+        // Check if total_deposited or total_borrowed is zero
+        if total_deposited == 0 || total_borrowed == 0 {
+            return 0;
+        }
+
+        (total_borrowed * decimals_factor * 100) / total_deposited
+    }
+
     // Borrower interest rate = lender APR + dev_fee
     fn calculate_interest_rate(&mut self) -> u128 {
         let apr = self.calculate_apr();
@@ -628,15 +1883,36 @@ where VftClient: Vft, {
 
     // Refresh utilization_factor, state.apr (lender APR), and state.interest_rate (borrower rate).
     // Call this whenever total_deposited or total_borrowed changes.
-    pub fn refresh_rates(&mut self) {
+    pub(crate) fn refresh_rates(&mut self) {
         self.calculate_interest_rate();
     }
     
+    // Interest owed on `loan_amount` after `time_diff_seconds` at `interest_rate`
+    pub(crate) fn accrued_interest(config: &Config, loan_amount: u128, interest_rate: u128, time_diff_seconds: u128) -> u128 {
+        // let interest_rate_amount = (loan_amount * interest_rate * time_diff_seconds)
+        // / (config.year_in_seconds * decimals_factor * 100);
+        // Refactor interest rate amount calculation to prevent overflow and maintain precision
+        let denominator = config
+            .year_in_seconds
+            .saturating_mul(config.decimals_factor)
+            .saturating_mul(100);
+
+        loan_amount
+            .saturating_mul(interest_rate)
+            .saturating_mul(time_diff_seconds)
+            .checked_div(denominator)
+            .unwrap_or(0)
+    }
+
     // Calculate accrued loan interest and add it to the user's loan_amount.
     // Uses borrower interest rate (lender APR + dev_fee).
-    pub fn calculate_loan_interest_rate_amount(&mut self, user: ActorId) -> String {
+    pub(crate) fn calculate_loan_interest_rate_amount(&mut self, user: ActorId) -> String {
         // Refresh rates BEFORE borrowing state_mut to avoid aliased mutable references.
-        let interest_rate = self.calculate_interest_rate();
+        // Vaults accrue the stability fee instead of the utilization-based rate.
+        let interest_rate = match self.state_ref().cdp.as_ref() {
+            Some(cdp) => cdp.stability_fee,
+            None => self.calculate_interest_rate(),
+        };
         let state_mut = self.state_mut();
         let user_info = match state_mut.users.get_mut(&user) {
             Some(u) => u,
@@ -649,31 +1925,29 @@ where VftClient: Vft, {
         let time_diff_seconds = current_timestamp.saturating_sub(user_info.borrow_last_updated) / 1000;
         let decimals_factor = state_mut.config.decimals_factor;
 
-        // let interest_rate_amount = (loan_amount * interest_rate * time_diff_seconds)
-            // / (state_mut.config.year_in_seconds * decimals_factor * 100);
-            // Refactor interest rate amount calculation to prevent overflow and maintain precision
-           let denominator = state_mut
-                .config
-                .year_in_seconds
-                .saturating_mul(decimals_factor)
-                .saturating_mul(100);
-
-            let interest_rate_amount = loan_amount
-                .saturating_mul(interest_rate)
-                .saturating_mul(time_diff_seconds)
-                .checked_div(denominator)
-                .unwrap_or(0);       
-
+        let interest_rate_amount = Self::accrued_interest(&state_mut.config, loan_amount, interest_rate, time_diff_seconds);
 
         user_info.loan_amount = user_info.loan_amount.saturating_add(interest_rate_amount);
         user_info.loan_amount_usdc = user_info.loan_amount / decimals_factor;
         user_info.borrow_last_updated = current_timestamp;
 
+        // Stability fees count towards the vault debt ceiling
+        if let Some(cdp) = state_mut.cdp.as_mut() {
+            cdp.accrued_fees = cdp.accrued_fees.saturating_add(interest_rate_amount);
+            state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(interest_rate_amount);
+        }
+
+        // Stable-rate debt accrues at the rate locked for the user
+        stable_rate::accrue_stable_debt(user_info, &state_mut.config, current_timestamp);
+
+        // Term loans accrue at their own locked rates
+        term_loans::accrue_user_term_loans(state_mut, &user, current_timestamp);
+
         format!("Loan Interest Rate Amount: {:?}", interest_rate_amount)
     }
 
     //Calculate Loan Interest Rate Amount for all users
-    pub fn calculate_all_loan_interest_rate_amounts(&mut self) -> Result<(), String> {
+    pub(crate) fn calculate_all_loan_interest_rate_amounts(&mut self) -> Result<(), String> {
         let state_mut = self.state_mut();
 
         for user in state_mut.users.keys().cloned().collect::<Vec<_>>() {
@@ -689,50 +1963,162 @@ where VftClient: Vft, {
 
     //Liquidate Loan
     pub async fn liquidate_user_loan(&mut self, user: ActorId) -> Result<(), String> {
+        // Vault debt has to be burned by the liquidator, see `liquidate_vault`
+        if self.state_mut().cdp.is_some() {
+            let error_message = ERROR_VAULT_LIQUIDATION_REQUIRED.to_string();
+            self.notify_error(error_message.clone());
+            return Err(error_message);
+        }
+
+        locks::acquire(self, &[user])?;
+        let result = self.liquidate_loan(user).await;
+        locks::release(self, &[user]);
+        result
+    }
+
+    pub(crate) async fn liquidate_loan(&mut self, user: ActorId) -> Result<(), String> {
         let state_mut = self.state_mut();
         let owner = state_mut.owner;
-        let protocol_ltv = state_mut.ltv;
+
+        // Writing off vault debt without burning stablecoin would leave it unbacked
+        if state_mut.cdp.is_some() {
+            return Ok(());
+        }
+
         let user_info = match state_mut.users.get_mut(&user) {
             Some(u) => u,
             None => return Ok(()),
         };
 
         let loan_amount = user_info.loan_amount;
+        let stable_loan_amount = user_info.stable_loan_amount;
+        let stable_rate = user_info.stable_rate;
         let balance_vara = user_info.balance_vara;
       
         let locked = (balance_vara * user_info.ltv) / 100;
 
+        // Token collaterals are seized in the same proportion as VARA
+        let seized_tokens = user_info
+            .collateral_balances
+            .iter()
+            .map(|(asset, balance)| (*asset, balance.saturating_mul(user_info.ltv) / 100))
+            .filter(|(_, seized)| *seized > 0)
+            .collect::<Vec<_>>();
+
+        // Reserve loans are closed together with the primary loan
+        let reserve_loans = state_mut
+            .reserves
+            .iter()
+            .filter_map(|(asset, reserve)| {
+                reserve
+                    .positions
+                    .get(&user)
+                    .filter(|position| position.loan_amount > 0)
+                    .map(|position| (*asset, position.loan_amount))
+            })
+            .collect::<Vec<_>>();
+
+        // Term loans are closed as well, whatever their maturity
+        let user_term_loans = state_mut
+            .term_loans
+            .iter()
+            .filter(|(_, term_loan)| term_loan.borrower == user)
+            .map(|(loan_id, term_loan)| (*loan_id, term_loan.clone()))
+            .collect::<Vec<_>>();
+
         //Condition to liquidate loan
-        if user_info.ltv >= protocol_ltv && locked > 0 {
+        if user_info.ltv >= user_info.liquidation_threshold && (locked > 0 || !seized_tokens.is_empty()) {
             //Set user loan status to false and reset all loan info values
             user_info.balance_vara = user_info.balance_vara.saturating_sub(locked);
+            state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_sub(locked);
+            for (asset, seized) in seized_tokens.iter() {
+                Self::debit_collateral_balance(user_info, *asset, *seized);
+            }
             user_info.is_loan_active = false;
             user_info.loan_amount = 0;
             user_info.loan_amount_usdc = 0;
+            user_info.stable_loan_amount = 0;
+            user_info.stable_rate = 0;
+            for (asset, reserve_loan) in reserve_loans.iter() {
+                let reserve = state_mut.reserves.get_mut(asset).unwrap();
+                if let Some(position) = reserve.positions.get_mut(&user) {
+                    position.loan_amount = 0;
+                }
+                reserve.total_borrowed = reserve.total_borrowed.saturating_sub(*reserve_loan);
+            }
+            for (loan_id, term_loan) in user_term_loans.iter() {
+                state_mut.term_loans.remove(loan_id);
+                state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(term_loan.principal);
+            }
             self.update_user_ltv(user);
             self.calculate_cv(user);
             self.calculate_mla(user);
-            state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(loan_amount);
+            state_mut.total_borrowed = state_mut.total_borrowed.saturating_sub(loan_amount.saturating_add(stable_loan_amount));
             Self::update_user_available_to_withdraw_vara(user_info);
 
             // Transfer seized collateral to protocol owner
-            if let Err(_) = msg::send(
-                owner,
-                LiquidityEvent::LoanLiquidated { user, loan_amount, collateral_seized: locked },
-                locked,
-            ) {
-                // Roll back user state if the VARA transfer fails
-                let state_mut = self.state_mut();
-                let decimals_factor = state_mut.config.decimals_factor;
-                let user_info = state_mut.users.get_mut(&user).unwrap();
-                user_info.balance_vara = user_info.balance_vara.saturating_add(locked);
-                user_info.is_loan_active = true;
-                user_info.loan_amount = loan_amount;
-                user_info.loan_amount_usdc = loan_amount / decimals_factor;
-                state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(loan_amount);
-                self.notify_on(LiquidityEvent::Error(ERROR_TRANSFER_FAILED.to_string()))
-                    .expect("Notification Error");
-                return Err(ERROR_TRANSFER_FAILED.to_string());
+            if locked > 0 {
+                if let Err(_) = msg::send(
+                    owner,
+                    LiquidityEvent::LoanLiquidated { user, loan_amount, collateral_seized: locked },
+                    locked,
+                ) {
+                    // Roll back user state if the VARA transfer fails
+                    let state_mut = self.state_mut();
+                    let decimals_factor = state_mut.config.decimals_factor;
+                    let user_info = state_mut.users.get_mut(&user).unwrap();
+                    user_info.balance_vara = user_info.balance_vara.saturating_add(locked);
+                    state_mut.total_collateral_vara = state_mut.total_collateral_vara.saturating_add(locked);
+                    for (asset, seized) in seized_tokens.iter() {
+                        let balance = user_info.collateral_balances.entry(*asset).or_insert(0);
+                        *balance = balance.saturating_add(*seized);
+                    }
+                    user_info.is_loan_active = true;
+                    user_info.loan_amount = loan_amount;
+                    user_info.loan_amount_usdc = loan_amount / decimals_factor;
+                    user_info.stable_loan_amount = stable_loan_amount;
+                    user_info.stable_rate = stable_rate;
+                    state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(loan_amount.saturating_add(stable_loan_amount));
+                    for (asset, reserve_loan) in reserve_loans.iter() {
+                        let reserve = state_mut.reserves.get_mut(asset).unwrap();
+                        if let Some(position) = reserve.positions.get_mut(&user) {
+                            position.loan_amount = *reserve_loan;
+                        }
+                        reserve.total_borrowed = reserve.total_borrowed.saturating_add(*reserve_loan);
+                    }
+                    for (loan_id, term_loan) in user_term_loans.iter() {
+                        state_mut.term_loans.insert(*loan_id, term_loan.clone());
+                        state_mut.total_borrowed = state_mut.total_borrowed.saturating_add(term_loan.principal);
+                    }
+                    self.calculate_cv(user);
+                    self.calculate_mla(user);
+                    self.update_user_ltv(user);
+                    self.notify_on(LiquidityEvent::Error(ERROR_TRANSFER_FAILED.to_string()))
+                        .expect("Notification Error");
+                    return Err(ERROR_TRANSFER_FAILED.to_string());
+                }
+            }
+
+            // Seized token collateral stays in custody and is credited to the protocol owner
+            if !seized_tokens.is_empty() {
+                let current_timestamp = exec::block_timestamp() as u128;
+                let owner_info = state_mut.users
+                    .entry(owner)
+                    .or_insert_with(|| Self::create_new_user(current_timestamp));
+
+                for (asset, seized) in seized_tokens.iter() {
+                    let balance = owner_info.collateral_balances.entry(*asset).or_insert(0);
+                    *balance = balance.saturating_add(*seized);
+                }
+
+                self.calculate_cv(owner);
+                self.calculate_mla(owner);
+                Self::update_user_available_to_withdraw_vara(owner_info);
+
+                for (asset, seized) in seized_tokens.into_iter() {
+                    self.notify_on(LiquidityEvent::TokenCollateralSeized { user, asset, amount: seized })
+                        .expect("Notification Error");
+                }
             }
 
             // Emit liquidation event for off-chain tracking
@@ -747,54 +2133,486 @@ where VftClient: Vft, {
         Ok(())      
     }
 
+    // Remove `amount` of `asset` from the user's collateral, dropping empty entries
+    pub(crate) fn debit_collateral_balance(user_info: &mut UserInfo, asset: ActorId, amount: u128) {
+        if let Some(balance) = user_info.collateral_balances.get_mut(&asset) {
+            *balance = balance.saturating_sub(amount);
+            if *balance == 0 {
+                user_info.collateral_balances.remove(&asset);
+            }
+        }
+    }
+
     // Liquidate all loans
     async fn liquidate_all_loans(&mut self) -> Result<(), String> {
         let state_mut = self.state_mut();
 
+        // Vaults are left to keepers calling `liquidate_vault`
+        if state_mut.cdp.is_some() {
+            return Ok(());
+        }
+
         for user in state_mut.users.keys().cloned().collect::<Vec<_>>() {
             let user_info = state_mut.users.get(&user).unwrap();
-            //check if user has an active loan
-            if user_info.is_loan_active == true {
-                let _ = self.liquidate_user_loan(user).await;
+            //check if user has an active loan, positions with an operation in flight wait for the next run
+            if user_info.is_loan_active == true && !locks::is_locked(state_mut, &user) {
+                let _ = self.liquidate_loan(user).await;
             } 
         }
 
         Ok(())
     }
 
+    // Liquidate all VARA pool loans
+    async fn liquidate_all_vara_loans(&mut self) -> Result<(), String> {
+        let state_mut = self.state_mut();
+
+        for (user, position) in state_mut.vara_pool.positions.clone().into_iter() {
+            if position.loan_amount > 0 && !locks::is_locked(state_mut, &user) {
+                let _ = vara_pool::liquidate_vara_loan(self, user).await;
+            }
+        }
+
+        Ok(())
+    }
+
     // Supply methods
 
     pub async fn deposit_liquidity(&mut self, amount: u128) -> Result<(), String> {
-        supply::deposit_liquidity(self, amount).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::deposit_liquidity(self, amount).await;
+        locks::release(self, &[caller]);
+        result
     }
 
     pub async fn withdraw_liquidity(&mut self, amount: u128) -> Result<(), String> {
-        supply::withdraw_liquidity(self, amount).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::withdraw_liquidity(self, amount).await;
+        locks::release(self, &[caller]);
+        result
     }
 
     pub async fn withdraw_rewards(&mut self) -> Result<(), String> {
-        supply::withdraw_rewards(self).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::withdraw_rewards(self).await;
+        locks::release(self, &[caller]);
+        result
     }
 
     pub async fn deposit_collateral(&mut self) -> Result<(), String> {
-        supply::deposit_collateral(self).await
+        let caller = msg::source();
+        locks::acquire_payable(self, &[caller])?;
+        let result = supply::deposit_collateral(self).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn deposit_collateral_for(&mut self, borrower: ActorId) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire_payable(self, &[caller, borrower])?;
+        let result = supply::deposit_collateral_for(self, borrower).await;
+        locks::release(self, &[caller, borrower]);
+        result
     }
 
     pub async fn withdraw_collateral(&mut self, amount: u128) -> Result<(), String> {
-        supply::withdraw_collateral(self, amount).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::withdraw_collateral(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn deposit_token_collateral(&mut self, asset: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = collateral::deposit_token_collateral(self, asset, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn withdraw_token_collateral(&mut self, asset: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = collateral::withdraw_token_collateral(self, asset, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Reserve methods
+
+    pub async fn deposit_reserve_liquidity(&mut self, asset: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = reserves::deposit_reserve_liquidity(self, asset, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn withdraw_reserve_liquidity(&mut self, asset: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = reserves::withdraw_reserve_liquidity(self, asset, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn take_reserve_loan(&mut self, asset: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = reserves::take_reserve_loan(self, asset, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn pay_reserve_loan(&mut self, asset: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = reserves::pay_reserve_loan(self, asset, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // VARA pool methods
+
+    pub async fn deposit_vara_liquidity(&mut self) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire_payable(self, &[caller])?;
+        let result = vara_pool::deposit_vara_liquidity(self).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn withdraw_vara_liquidity(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = vara_pool::withdraw_vara_liquidity(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn deposit_stable_collateral(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = vara_pool::deposit_stable_collateral(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn withdraw_stable_collateral(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = vara_pool::withdraw_stable_collateral(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn take_vara_loan(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = vara_pool::take_vara_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn pay_vara_loan(&mut self) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire_payable(self, &[caller])?;
+        let result = vara_pool::pay_vara_loan(self).await;
+        locks::release(self, &[caller]);
+        result
     }
 
     // Borrow methods
 
+    pub fn set_user_emode(&mut self, category_id: u8) -> Result<(), String> {
+        locks::ensure_idle(self, &[msg::source()])?;
+        emode::set_user_emode(self, category_id)
+    }
+
     pub async fn take_loan(&mut self, amount: u128) -> Result<(), String> {
-        borrow::take_loan(self, amount).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = borrow::take_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub fn approve_delegation(&mut self, delegatee: ActorId, amount: u128) -> Result<(), String> {
+        locks::ensure_idle(self, &[msg::source()])?;
+        borrow::approve_delegation(self, delegatee, amount)
+    }
+
+    pub async fn take_loan_on_behalf(&mut self, delegator: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller, delegator])?;
+        let result = borrow::take_loan_on_behalf(self, delegator, amount).await;
+        locks::release(self, &[caller, delegator]);
+        result
+    }
+
+    pub async fn take_term_loan(&mut self, amount: u128, term: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = term_loans::take_term_loan(self, amount, term).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn repay_term_loan(&mut self, loan_id: u64, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = term_loans::repay_term_loan(self, loan_id, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn liquidate_overdue_term_loan(&mut self, loan_id: u64) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = term_loans::liquidate_overdue_term_loan(self, loan_id).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Run supply and borrow actions in one message. Token flows are netted and
+    // settled at the end; if any action or the settlement fails, nothing changes.
+    pub async fn multicall(&mut self, actions: Vec<Action>) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire_payable(self, &[caller])?;
+        let result = multicall::multicall(self, actions).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Repay all variable debt, claim rewards and withdraw all VARA collateral at once
+    pub async fn close_position(&mut self) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = positions::close_position(self).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Repay part of the loan with the caller's own VARA collateral at a reduced fee
+    pub async fn self_liquidate(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = positions::self_liquidate(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Loop borrow -> swap -> deposit until the position reaches `target_ltv`
+    pub async fn leverage(&mut self, target_ltv: u128, max_iterations: u32) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = leverage::leverage(self, target_ltv, max_iterations).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Loop withdraw -> swap -> repay until the position is back down to `target_ltv`
+    pub async fn deleverage(&mut self, target_ltv: u128, max_iterations: u32) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = leverage::deleverage(self, target_ltv, max_iterations).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    // Swap one collateral for another through the DEX adapter, keeping the loan open.
+    // `ActorId::zero()` stands for VARA.
+    pub async fn swap_collateral(
+        &mut self,
+        from_asset: ActorId,
+        to_asset: ActorId,
+        amount: u128,
+        min_out: u128,
+    ) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = collateral_swap::swap_collateral(self, from_asset, to_asset, amount, min_out).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn lock_governance_tokens(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = governance::lock_governance_tokens(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn unlock_governance_tokens(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = governance::unlock_governance_tokens(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub fn propose(&mut self, change: ConfigChange) -> Result<u64, String> {
+        governance::propose(self, change)
+    }
+
+    pub fn vote(&mut self, proposal_id: u64, support: bool) -> Result<(), String> {
+        governance::vote(self, proposal_id, support)
+    }
+
+    pub fn queue_proposal(&mut self, proposal_id: u64) -> Result<(), String> {
+        governance::queue_proposal(self, proposal_id)
+    }
+
+    pub fn execute_proposal(&mut self, proposal_id: u64) -> Result<(), String> {
+        governance::execute_proposal(self, proposal_id)
+    }
+
+    pub async fn deposit_savings(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = savings::deposit_savings(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn withdraw_savings(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = savings::withdraw_savings(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn liquidate_vault(&mut self, user: ActorId) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller, user])?;
+        let result = cdp::liquidate_vault(self, user).await;
+        locks::release(self, &[caller, user]);
+        result
+    }
+
+    pub async fn take_stable_loan(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = stable_rate::take_stable_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn pay_stable_loan(&mut self, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = stable_rate::pay_stable_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
     }
 
     pub async fn pay_all_loan(&mut self) -> Result<(), String> {
-        borrow::pay_all_loan(self).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = borrow::pay_all_loan(self).await;
+        locks::release(self, &[caller]);
+        result
     }
 
     pub async fn pay_loan(&mut self, amount: u128) -> Result<(), String> {
-        borrow::pay_loan(self, amount).await
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = borrow::pay_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn repay_for(&mut self, borrower: ActorId, amount: u128) -> Result<(), String> {
+        let caller = msg::source();
+        locks::acquire(self, &[caller, borrower])?;
+        let result = borrow::repay_for(self, borrower, amount).await;
+        locks::release(self, &[caller, borrower]);
+        result
+    }
+
+    // Isolated market methods
+    // Same flows as the default market, scoped to `market_id`.
+
+    pub async fn market_deposit_liquidity(&mut self, market_id: u32, amount: u128) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::deposit_liquidity(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_withdraw_liquidity(&mut self, market_id: u32, amount: u128) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::withdraw_liquidity(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_withdraw_rewards(&mut self, market_id: u32) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::withdraw_rewards(self).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_deposit_collateral(&mut self, market_id: u32) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire_payable(self, &[caller])?;
+        let result = supply::deposit_collateral(self).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_withdraw_collateral(&mut self, market_id: u32, amount: u128) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = supply::withdraw_collateral(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_take_loan(&mut self, market_id: u32, amount: u128) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = borrow::take_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_pay_loan(&mut self, market_id: u32, amount: u128) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = borrow::pay_loan(self, amount).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_pay_all_loan(&mut self, market_id: u32) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        let caller = msg::source();
+        locks::acquire(self, &[caller])?;
+        let result = borrow::pay_all_loan(self).await;
+        locks::release(self, &[caller]);
+        result
+    }
+
+    pub async fn market_liquidate_user_loan(&mut self, market_id: u32, user: ActorId) -> Result<(), String> {
+        self.enter_market(market_id)?;
+        self.liquidate_user_loan(user).await
     }
 }
\ No newline at end of file
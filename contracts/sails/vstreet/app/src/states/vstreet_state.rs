@@ -22,18 +22,289 @@ pub struct UserInfo {
     pub loan_amount_usdc: u128,
     pub is_loan_active: bool,
     pub ltv: u128,
+    // LTV (percentage) at which the position becomes liquidatable, weighted across collaterals
+    pub liquidation_threshold: u128,
+    // VFT collateral asset -> raw token units deposited
+    pub collateral_balances: BTreeMap<ActorId, u128>,
+    // Primary stable VFT posted as collateral for VARA pool loans
+    pub stable_collateral: u128,
+    // Stable collateral valued in VARA (price inverted)
+    pub stable_cv: u128,
+    pub vara_mla: u128,
+    pub vara_ltv: u128,
+    // Stable-rate debt, tracked apart from the variable `loan_amount`
+    pub stable_loan_amount: u128,
+    pub stable_rate: u128,
+    pub stable_borrow_last_updated: u128,
+    // E-mode category the user opted into, 0 when outside e-mode
+    pub emode_category: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct CollateralAsset {
+    // Account allowed to push price updates for this asset
+    pub price_feed: ActorId,
+    // Price of one whole token, same scale as `Config::vara_price`
+    pub price: u128,
+    // Raw units in one whole token
+    pub one_token: u128,
+    // Percentage of the collateral value that can be borrowed (e.g. 70% = 70)
+    pub collateral_factor: u128,
+    // Percentage LTV at which positions backed by this asset get liquidated
+    pub liquidation_threshold: u128,
+    pub total_deposited: u128,
+    pub enabled: bool,
+    // Global debt ceiling while the asset is listed in isolation, 0 when it is not
+    pub debt_ceiling: u128,
+    // Assets that users isolated on this collateral may borrow
+    pub isolation_borrowable: Vec<ActorId>,
+}
+
+// Efficiency mode category for correlated assets (e.g. stables against stables).
+// Users in the category get its LTV and liquidation threshold on the listed
+// collaterals and can only borrow the listed assets.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct EModeCategory {
+    pub ltv: u128,
+    pub liquidation_threshold: u128,
+    pub assets: Vec<ActorId>,
+    pub label: String,
+}
+
+// Stable VFT reserve hosted next to the primary `vft_contract_id` pool.
+// Interest accrues per reserve: borrowers' debt grows and suppliers are
+// credited pro-rata with the interest net of the dev fee.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Reserve {
+    // Raw units in one whole token, used to value debt against the CV
+    pub decimals_factor: u128,
+    pub total_deposited: u128,
+    pub total_borrowed: u128,
+    pub accrued_fees: u128,
+    pub utilization_factor: u128,
+    pub apr: u128,
+    pub interest_rate: u128,
+    pub base_rate: u128,
+    pub risk_multiplier: u128,
+    pub dev_fee: u128,
+    pub supply_cap: u128,
+    pub borrow_cap: u128,
+    pub enabled: bool,
+    pub last_updated: u128,
+    pub positions: BTreeMap<ActorId, ReservePosition>,
+}
+
+// Rate model and caps an administrator sets when listing or updating a reserve
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct ReserveParams {
+    pub base_rate: u128,
+    pub risk_multiplier: u128,
+    pub dev_fee: u128,
+    pub supply_cap: u128,
+    pub borrow_cap: u128,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct ReservePosition {
+    pub balance: u128,
+    pub loan_amount: u128,
+}
+
+// Fixed-rate loan drawn from the primary pool. The rate is locked at origination.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct TermLoan {
+    pub borrower: ActorId,
+    // Outstanding principal, interest is repaid before principal
+    pub principal: u128,
+    // Outstanding principal plus accrued interest
+    pub amount: u128,
+    pub rate: u128,
+    pub start: u128,
+    pub maturity: u128,
+    pub last_updated: u128,
+}
+
+// Vault mode: loans mint the primary VFT instead of lending supplier liquidity
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Cdp {
+    // Annual fee on vault debt, same units as the variable interest rate
+    pub stability_fee: u128,
+    // Maximum stablecoin debt across all vaults
+    pub debt_ceiling: u128,
+    // Stability fees accrued so far
+    pub accrued_fees: u128,
+}
+
+// Savings module for the primary stable. Balances are stored normalized by the
+// cumulative rate accumulator `chi`, so a balance is `normalized * chi / precision`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Savings {
+    // Annual savings rate, same units as the variable interest rate
+    pub rate: u128,
+    pub chi: u128,
+    pub last_updated: u128,
+    pub total_normalized: u128,
+    pub balances: BTreeMap<ActorId, u128>,
+}
+
+// Config change that can be proposed by governance or queued by an admin,
+// applied once the timelock expires
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum ConfigChange {
+    Ltv(u128),
+    InterestRates { base_rate: u128, risk_multiplier: u128 },
+    Caps { supply_cap: u128, borrow_cap: u128, collateral_cap: u128 },
+    PriceFeed { asset: ActorId, price_feed: ActorId },
+    SavingsRate(u128),
+}
+
+// Step of a multicall, executed for the caller
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum Action {
+    DepositLiquidity(u128),
+    WithdrawLiquidity(u128),
+    WithdrawRewards,
+    // Raw VARA units, paid from the value attached to the multicall
+    DepositCollateral(u128),
+    // TVARA units, like `withdraw_collateral`
+    WithdrawCollateral(u128),
+    TakeLoan(u128),
+    PayLoan(u128),
+    PayAllLoan,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum ProposalStatus {
+    Active,
+    Defeated,
+    Queued,
+    Executed,
+    Canceled,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Proposal {
+    pub proposer: ActorId,
+    pub change: ConfigChange,
+    // Votes are weighted by governance tokens locked before this timestamp
+    pub snapshot: u128,
+    pub voting_ends: u128,
+    pub for_votes: u128,
+    pub against_votes: u128,
+    pub voters: Vec<ActorId>,
+    pub status: ProposalStatus,
+    // Earliest execution time once queued
+    pub eta: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Governance {
+    pub token: Option<ActorId>,
+    pub voting_period: u128,
+    pub timelock_delay: u128,
+    pub proposal_threshold: u128,
+    pub quorum: u128,
+    pub total_locked: u128,
+    // Locked governance token balance history per holder as (timestamp, balance)
+    pub checkpoints: BTreeMap<ActorId, Vec<(u128, u128)>>,
+    pub proposals: BTreeMap<u64, Proposal>,
+    pub next_proposal_id: u64,
+}
+
+// Positions whose LTV came within `warning_band` percentage points of their
+// liquidation threshold, by the block they were flagged in. Kept sorted so the
+// query can be paged.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct RiskWatch {
+    pub warning_band: u128,
+    pub at_risk: BTreeMap<ActorId, u32>,
+}
+
+// Operations waiting on a reply, by the block they started in. A user with an
+// entry cannot start another one; `global` is held by admin batch jobs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct OperationLocks {
+    pub users: BTreeMap<ActorId, u32>,
+    pub global: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub enum PendingStatus {
+    // Still waiting on replies
+    InFlight,
+    // Trapped before any call went through, the positions were restored
+    RolledBack,
+    // Tokens moved but the accounting never caught up, left for an operator to settle
+    NeedsReview,
+}
+
+// Operation that awaits external calls, keyed by the id of the message that started it.
+// Entries are dropped when the operation finishes; recovered ones stay until cleared.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct PendingOperation {
+    pub users: Vec<ActorId>,
+    pub started_at: u32,
+    pub status: PendingStatus,
+    // Calls to other programs (transfers, mints, burns, swaps) sent so far,
+    // and those the reply hook saw go through or fail
+    pub sent_transfers: u32,
+    pub confirmed_transfers: u32,
+    pub failed_transfers: u32,
+    // Positions of `users` when the operation started
+    pub snapshot: Vec<(ActorId, PositionSnapshot)>,
+}
+
+// Everything a recoverable operation may change for one user
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct PositionSnapshot {
+    pub user_info: Option<UserInfo>,
+    // Reserve asset -> position, for the reserves the user has a position in
+    pub reserve_positions: BTreeMap<ActorId, ReservePosition>,
+    pub vara_pool_position: Option<ReservePosition>,
+    pub term_loans: BTreeMap<u64, TermLoan>,
+    // Delegations the user gave or received
+    pub delegations: BTreeMap<(ActorId, ActorId), u128>,
+    // Normalized savings balance
+    pub savings_balance: Option<u128>,
+}
+
+// Swap venue used to loop borrowed stable into VARA collateral and back
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct SwapVenue {
+    pub program: ActorId,
+    // Basis points a swap may fill below the oracle price
+    pub max_slippage: u128,
 }
 
 #[derive(Clone, Encode, TypeInfo)]
 pub struct VstreetState {
     pub owner: ActorId,
     pub admins: Vec<ActorId>,
+    pub risk_managers: Vec<ActorId>,
     pub vft_contract_id: Option<ActorId>,
     pub total_deposited: u128,
     pub total_borrowed: u128,
+    pub total_collateral_vara: u128,
     pub available_rewards_pool: u128,
     pub total_rewards_distributed: u128,
     pub users: BTreeMap<ActorId, UserInfo>,
+    pub collateral_assets: BTreeMap<ActorId, CollateralAsset>,
+    pub emode_categories: BTreeMap<u8, EModeCategory>,
+    pub reserves: BTreeMap<ActorId, Reserve>,
+    // Native VARA pool, lent against the primary stable VFT
+    pub vara_pool: Reserve,
+    pub total_stable_collateral: u128,
+    pub term_loans: BTreeMap<u64, TermLoan>,
+    pub next_term_loan_id: u64,
+    pub cdp: Option<Cdp>,
+    pub savings: Savings,
+    pub governance: Governance,
+    pub swap_venue: Option<SwapVenue>,
+    pub risk_watch: RiskWatch,
+    pub locks: OperationLocks,
+    pub pending_operations: BTreeMap<MessageId, PendingOperation>,
+    // (delegator, delegatee) -> remaining amount the delegatee may borrow against the delegator
+    pub delegations: BTreeMap<(ActorId, ActorId), u128>,
     pub utilization_factor: u128,
     pub interest_rate: u128,
     pub apr: u128,
@@ -54,7 +325,23 @@ pub struct Config {
     pub max_collateral_withdraw: u128,
     pub max_liquidity_deposit: u128,
     pub max_liquidity_withdraw: u128,
-    pub min_rewards_withdraw: u128
+    pub min_rewards_withdraw: u128,
+    pub supply_cap: u128,
+    pub borrow_cap: u128,
+    pub collateral_cap: u128,
+    // Term loan durations in milliseconds
+    pub min_loan_term: u128,
+    pub max_loan_term: u128,
+    // Added to the variable interest rate when a term loan rate is locked
+    pub term_rate_premium: u128,
+    // Percentage of the outstanding amount charged to repay a term loan before maturity
+    pub early_repayment_fee: u128,
+    // Added to the variable interest rate when a stable rate is locked
+    pub stable_rate_premium: u128,
+    // Gap between a locked stable rate and the current one that allows a rebalance
+    pub stable_rate_rebalance_delta: u128,
+    // Percentage of the repaid debt taken on top, in VARA, when a borrower self-liquidates
+    pub self_liquidation_fee: u128,
 }
 
 impl Default for Config {
@@ -71,7 +358,18 @@ impl Default for Config {
             max_collateral_withdraw: 100000000000000000000,
             max_liquidity_deposit: 100000000000000000000,
             max_liquidity_withdraw: 100000000000000000000,
-            min_rewards_withdraw: 100000
+            min_rewards_withdraw: 100000,
+            // Protocol-wide caps stay open until the risk role sets them
+            supply_cap: u128::MAX,
+            borrow_cap: u128::MAX,
+            collateral_cap: u128::MAX,
+            min_loan_term: 604_800_000,      // 7 days
+            max_loan_term: 31_536_000_000,   // 365 days
+            term_rate_premium: 20_000,       // 2% * DECIMALS_FACTOR
+            early_repayment_fee: 1,          // 1%
+            stable_rate_premium: 30_000,     // 3% * DECIMALS_FACTOR
+            stable_rate_rebalance_delta: 50_000, // 5% * DECIMALS_FACTOR
+            self_liquidation_fee: 2,         // 2%
         }
     }
 }
\ No newline at end of file